@@ -1,18 +1,41 @@
-use std::any::{Any, TypeId};
+pub mod recording;
+pub use recording::*;
 
-use winit::event::WindowEvent;
+use std::{any::Any, path::Path, time::Duration};
+
+use winit::window::WindowId;
 
 use crate::{
     core::{
-        sf_events::{EventDispatcher, Eventable, WindowRedrawRequestedEvent},
+        sf_error::SfResult,
+        sf_events::{
+            EventDispatcher, EventHandler, EventLog, EventQueue, Eventable, LoggedEvent,
+            WindowRedrawRequestedEvent,
+        },
         sf_layers::LayerStack,
+        sf_time::{FrameClock, FrameTiming},
     },
-    info_core,
+    error_core,
 };
 
 pub struct EventSystem<'a> {
     pub layer_stack: LayerStack,
     pub non_layer_event_dispatcher: EventDispatcher<'a>,
+    pub frame_clock: FrameClock,
+    /// Redraws of this window drive the frame, redraws of other windows are dispatched like any other event.
+    pub main_window: Option<WindowId>,
+    pub event_queue: EventQueue,
+    recorder: Option<EventRecorder>,
+    event_log: Option<EventLog>,
+}
+
+/// How many frames [`EventSystem::get_event_log`] keeps.
+pub const EVENT_LOG_FRAMES: usize = 120;
+
+impl Default for EventSystem<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> EventSystem<'a> {
@@ -20,20 +43,468 @@ impl<'a> EventSystem<'a> {
         Self {
             layer_stack: LayerStack::new(),
             non_layer_event_dispatcher: EventDispatcher::new(),
+            frame_clock: FrameClock::default(),
+            main_window: None,
+            event_queue: EventQueue::new(),
+            recorder: None,
+            event_log: None,
+        }
+    }
+
+    /// Events are only logged once this was called and while the log is enabled and not paused,
+    /// logging costs a string or two per event.
+    pub fn get_event_log(&mut self) -> EventLog {
+        self.event_log
+            .get_or_insert_with(|| EventLog::new(EVENT_LOG_FRAMES))
+            .clone()
+    }
+
+    /// Records the engine events and frames from now on to `path`, see [`EventReplayer`] to play them back.
+    /// Replays match best when recording starts before the first frame.
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> SfResult<()> {
+        self.stop_recording()?;
+        self.recorder = Some(EventRecorder::create(path, &self.frame_clock)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> SfResult<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
         }
     }
 
-    pub fn on_event<E: Eventable>(&mut self, event: E) {
-        if event.type_id() == TypeId::of::<WindowRedrawRequestedEvent>() {
-            for layer in self.layer_stack.layers.iter_mut().rev() {
-                layer.on_update();
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Returns a queue for posting and sending events from game code, see [`EventQueue`].
+    pub fn get_event_queue(&self) -> EventQueue {
+        self.event_queue.clone()
+    }
+
+    /// Delivers `event` at the start of the next frame.
+    pub fn post<E: Eventable>(&self, event: E) {
+        self.event_queue.post(event);
+    }
+
+    /// Delivers `event` right away. Events sent by game code aren't recorded, a replay runs the
+    /// same code, which sends them again.
+    pub fn send<E: Eventable>(&mut self, mut event: E) {
+        self.handle_event(&mut event, Capture::Live);
+    }
+
+    /// Like [`EventSystem::send`], for events arriving from other threads through an
+    /// [`EventSender`](crate::core::sf_events::EventSender).
+    pub fn send_boxed(&mut self, mut event: Box<dyn Eventable>) {
+        self.handle_event(event.as_mut(), Capture::Live);
+    }
+
+    fn on_frame(&mut self) {
+        let frame_timing = self.frame_clock.tick();
+        self.run_frame(frame_timing);
+    }
+
+    /// Runs a frame that took `delta` instead of measuring it, used by [`EventReplayer`].
+    pub fn replay_frame(&mut self, delta: Duration) {
+        let frame_timing = self.frame_clock.advance(delta);
+        self.run_frame(frame_timing);
+    }
+
+    fn run_frame(&mut self, frame_timing: FrameTiming) {
+        if let Some(event_log) = &self.event_log {
+            event_log.begin_frame();
+        }
+        if let Some(recorder) = &mut self.recorder {
+            let result = recorder.record_frame(frame_timing.timestep.get_delta());
+            self.on_recording_result(result);
+        }
+
+        for mut event in self.event_queue.take_posted() {
+            self.dispatch(event.as_mut(), Capture::Live);
+        }
+
+        for fixed_timestep in frame_timing.fixed_timesteps {
+            for layer in self.layer_stack.iter_bottom_up_mut() {
+                layer.on_fixed_update(fixed_timestep);
             }
-            return;
         }
+        for layer in self.layer_stack.iter_bottom_up_mut() {
+            layer.on_update(frame_timing.timestep);
+        }
+        self.layer_stack.apply_commands();
+    }
+
+    pub fn on_event<E: Eventable>(&mut self, mut event: E) {
+        self.on_dynamic_event(&mut event);
+    }
+
+    pub fn on_boxed_event(&mut self, mut event: Box<dyn Eventable>) {
+        self.on_dynamic_event(event.as_mut());
+    }
+
+    /// Takes an event from the window manager, recording it while a recording runs.
+    pub fn on_dynamic_event(&mut self, event: &mut dyn Eventable) {
+        self.take_event(event, Capture::Live);
+    }
+
+    /// Takes an event of a recording, see [`EventReplayer`]. Overlays like the GUI capture events
+    /// based on raw window events, which aren't recorded, so instead of asking them the overlay
+    /// that captured the event live, if any, is taken from the recording.
+    pub fn on_replayed_event(&mut self, event: &mut dyn Eventable, captured_by: Option<&str>) {
+        self.take_event(event, Capture::Replayed(captured_by));
+    }
+
+    fn take_event(&mut self, event: &mut dyn Eventable, capture: Capture) {
+        let delivery = self.handle_event(event, capture);
+        if let Some(delivery) = delivery
+            && let Some(recorder) = &mut self.recorder
+        {
+            let result = recorder.record_event(event, delivery.captured_by);
+            self.on_recording_result(result);
+        }
+    }
+
+    /// Redraws of the main window run a frame, other events are dispatched.
+    /// Events sent through the [`EventQueue`] meanwhile are delivered afterwards.
+    /// Returns `None` for frames.
+    fn handle_event(&mut self, event: &mut dyn Eventable, capture: Capture) -> Option<Delivery> {
+        let delivery = if let Some(redraw) =
+            (&*event as &dyn Any).downcast_ref::<WindowRedrawRequestedEvent>()
+            && self.main_window.is_none_or(|id| id == redraw.window_id)
+        {
+            self.on_frame();
+            None
+        } else {
+            Some(self.dispatch(event, capture))
+        };
+
+        while let Some(mut sent) = self.event_queue.take_sent() {
+            self.dispatch(sent.as_mut(), Capture::Live);
+        }
+        delivery
+    }
+
+    /// A recording that failed to write stops, the game keeps running.
+    fn on_recording_result(&mut self, result: SfResult<()>) {
+        if let Err(e) = result {
+            error_core!("{}, recording stopped", e);
+            self.recorder = None;
+        }
+    }
 
-        self.non_layer_event_dispatcher.dispatch(&event);
-        for layer in self.layer_stack.layers.iter_mut().rev() {
-            layer.on_event(&event);
+    /// Delivers `event` to the non layer listeners, then top-down to the layers subscribed to
+    /// its categories until it's handled. On replay the recording decides which overlay handles it.
+    fn dispatch(&mut self, event: &mut dyn Eventable, capture: Capture) -> Delivery {
+        let delivery = self.deliver(event, capture);
+        if let Some(event_log) = &self.event_log
+            && event_log.is_logging()
+        {
+            event_log.log(LoggedEvent::new(event, delivery.handled_by.clone()));
         }
+        delivery
+    }
+
+    fn deliver(&mut self, event: &mut dyn Eventable, capture: Capture) -> Delivery {
+        let dispatcher = &mut self.non_layer_event_dispatcher;
+        if let Some(id) = dispatcher.dispatch_and_get_handler(event) {
+            let name = dispatcher.get_listener_name(id);
+            return Delivery {
+                handled_by: Some(EventHandler::Listener { id, name }),
+                captured_by: None,
+            };
+        }
+        if event.is_handled() {
+            return Delivery::default();
+        }
+
+        let categories = event.get_category_flags();
+        let overlay_count = self.layer_stack.get_overlay_count();
+        let mut captured_by = None;
+        for overlay in self.layer_stack.iter_top_down_mut().take(overlay_count) {
+            if !overlay.get_event_categories().intersects(categories) {
+                continue;
+            }
+            overlay.on_event(event);
+            let name = overlay.get_name();
+            match capture {
+                Capture::Live => {
+                    if event.is_handled() {
+                        captured_by = Some(name.clone());
+                        break;
+                    }
+                }
+                Capture::Replayed(replayed_captured_by) => {
+                    // Whatever the overlay decided without the raw window events is overruled.
+                    event.set_handled(false);
+                    if replayed_captured_by == Some(name.as_str()) {
+                        break;
+                    }
+                }
+            }
+        }
+        if let Capture::Replayed(Some(replayed_captured_by)) = capture {
+            event.set_handled(true);
+            captured_by = Some(replayed_captured_by.to_string());
+        }
+
+        let mut handled_by = captured_by.clone();
+        if handled_by.is_none() {
+            for layer in self.layer_stack.iter_top_down_mut().skip(overlay_count) {
+                if !layer.get_event_categories().intersects(categories) {
+                    continue;
+                }
+                layer.on_event(event);
+                if event.is_handled() {
+                    handled_by = Some(layer.get_name().clone());
+                    break;
+                }
+            }
+        }
+        self.layer_stack.apply_commands();
+        Delivery {
+            handled_by: handled_by.map(EventHandler::Layer),
+            captured_by,
+        }
+    }
+}
+
+/// Whether overlays decide themselves which events they capture.
+#[derive(Clone, Copy)]
+enum Capture<'c> {
+    Live,
+    /// The name of the overlay that captured the event live.
+    Replayed(Option<&'c str>),
+}
+
+/// Who took an event.
+#[derive(Default)]
+struct Delivery {
+    handled_by: Option<EventHandler>,
+    /// The overlay that handled the event, if it was one.
+    captured_by: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        sf_events::{EventCategory, EventCategoryFlags, ListenerOptions},
+        sf_layers::Layer,
+        sf_time::Timestep,
+        test_utils::{Log, LoggingLayer, LoggingListener, TestEvent},
+    };
+
+    /// Logs updates and events, posts "posted" and sends "sent" on its first update.
+    struct QueueingLayer {
+        name: String,
+        log: Log,
+        event_queue: EventQueue,
+        has_queued: bool,
+    }
+
+    impl Layer for QueueingLayer {
+        fn get_name(&mut self) -> &String {
+            &self.name
+        }
+        fn on_attach(&mut self) {}
+        fn on_detach(&mut self) {}
+        fn on_update(&mut self, _timestep: Timestep) {
+            self.log.borrow_mut().push("update".to_string());
+            if !self.has_queued {
+                self.has_queued = true;
+                self.event_queue.post(TestEvent::new("posted"));
+                self.event_queue.send(TestEvent::new("sent"));
+            }
+        }
+        fn on_event(&mut self, event: &mut dyn Eventable) {
+            self.log.borrow_mut().push(event.get_name().to_string());
+        }
+    }
+
+    fn redraw() -> WindowRedrawRequestedEvent {
+        WindowRedrawRequestedEvent {
+            name: String::from("WINDOW REDRAW REQUESTED EVENT"),
+            window_id: WindowId::from(0),
+            is_handled: false,
+        }
+    }
+
+    #[test]
+    fn sent_events_arrive_after_the_frame_and_posted_ones_next_frame() {
+        let log = Log::default();
+        let mut event_system = EventSystem::new();
+        let layer = QueueingLayer {
+            name: "game".to_string(),
+            log: log.clone(),
+            event_queue: event_system.get_event_queue(),
+            has_queued: false,
+        };
+        event_system.layer_stack.push_layer(Box::new(layer));
+
+        event_system.on_event(redraw());
+        assert_eq!(*log.borrow(), ["update", "sent"]);
+
+        event_system.on_event(redraw());
+        assert_eq!(*log.borrow(), ["update", "sent", "posted", "update"]);
+    }
+
+    #[test]
+    fn layers_and_listeners_only_get_their_categories() {
+        let log = Log::default();
+        let mut event_system = EventSystem::new();
+        event_system.main_window = Some(WindowId::from(0));
+        let _subscription = event_system
+            .non_layer_event_dispatcher
+            .add_category_listener(
+                EventCategory::UserCategory,
+                LoggingListener::new("listener", &log),
+            );
+        for (name, categories) in [
+            ("all", EventCategoryFlags::ALL),
+            ("keyboard", EventCategory::KeyboardCategory.into()),
+            (
+                "user",
+                EventCategory::KeyboardCategory | EventCategory::UserCategory,
+            ),
+        ] {
+            event_system.layer_stack.push_layer(Box::new(LoggingLayer {
+                categories,
+                ..LoggingLayer::new(name, &log)
+            }));
+        }
+        log.borrow_mut().clear();
+
+        event_system.send(TestEvent::new("game"));
+        event_system.send(WindowRedrawRequestedEvent {
+            name: String::from("redraw"),
+            window_id: WindowId::from(1),
+            is_handled: false,
+        });
+        assert_eq!(
+            *log.borrow(),
+            ["listener", "user game", "all game", "all redraw"]
+        );
+    }
+
+    #[test]
+    fn send_dispatches_immediately() {
+        let log = Log::default();
+        let mut event_system = EventSystem::new();
+        let layer = QueueingLayer {
+            name: "game".to_string(),
+            log: log.clone(),
+            event_queue: event_system.get_event_queue(),
+            has_queued: true,
+        };
+        event_system.layer_stack.push_layer(Box::new(layer));
+
+        event_system.send(TestEvent::new("now"));
+        event_system.post(TestEvent::new("later"));
+        assert_eq!(*log.borrow(), ["now"]);
+    }
+
+    #[test]
+    fn handled_events_stop_at_the_layer_that_handled_them() {
+        let log = Log::default();
+        let mut event_system = EventSystem::new();
+        event_system
+            .layer_stack
+            .push_layer(LoggingLayer::boxed("bottom", &log));
+        event_system.layer_stack.push_layer(Box::new(LoggingLayer {
+            handles_events: true,
+            ..LoggingLayer::new("handling", &log)
+        }));
+        event_system
+            .layer_stack
+            .push_layer(LoggingLayer::boxed("top", &log));
+        log.borrow_mut().clear();
+
+        let mut event = TestEvent::new("game");
+        event_system.on_dynamic_event(&mut event);
+        assert!(event.is_handled());
+        assert_eq!(*log.borrow(), ["top game", "handling game"]);
+
+        let _subscription = event_system
+            .non_layer_event_dispatcher
+            .on::<TestEvent>(|_| true);
+        event_system.send(TestEvent::new("for the listener"));
+        assert_eq!(*log.borrow(), ["top game", "handling game"]);
+    }
+
+    #[test]
+    fn event_log_keeps_frames_and_who_handled_the_events() {
+        let mut event_system = EventSystem::new();
+        event_system.send(TestEvent::new("before the log"));
+        let event_log = event_system.get_event_log();
+        let listener_id = event_system
+            .non_layer_event_dispatcher
+            .on_with::<TestEvent>(ListenerOptions::default().with_name("game"), |event| {
+                event.name == "for the listener"
+            })
+            .forget();
+        event_system.layer_stack.push_layer(Box::new(LoggingLayer {
+            handles_events: true,
+            ..LoggingLayer::new("handling", &Log::default())
+        }));
+
+        event_system.send(TestEvent::new("for the listener"));
+        event_system.on_event(redraw());
+        event_system.send(TestEvent::new("for the layer"));
+
+        let frames = event_log.get_frames();
+        let handlers: Vec<Vec<Option<EventHandler>>> = frames
+            .iter()
+            .map(|frame| frame.events.iter().map(|e| e.handled_by.clone()).collect())
+            .collect();
+        assert_eq!(
+            handlers,
+            [
+                vec![Some(EventHandler::Listener {
+                    id: listener_id,
+                    name: Some("game"),
+                })],
+                vec![Some(EventHandler::Layer("handling".to_string()))],
+            ]
+        );
+        assert_eq!(frames[1].index, 1);
+        assert!(frames[1].events[0].type_name.ends_with("TestEvent"));
+        assert_eq!(frames[1].events[0].description, "for the layer");
+    }
+
+    /// Fails the test when it's described, which the event log does for every event it keeps.
+    struct UndescribedEvent;
+
+    impl Eventable for UndescribedEvent {
+        fn get_name(&self) -> &str {
+            "undescribed"
+        }
+        fn to_string(&self) -> String {
+            panic!("described an event for a log that doesn't keep it");
+        }
+        fn is_handled(&self) -> bool {
+            false
+        }
+        fn set_handled(&mut self, _is_handled: bool) {}
+    }
+
+    #[test]
+    fn disabled_or_paused_event_log_skips_describing_events() {
+        let mut event_system = EventSystem::new();
+        let event_log = event_system.get_event_log();
+
+        event_log.set_enabled(false);
+        event_system.send(UndescribedEvent);
+        event_system.on_event(redraw());
+        assert!(event_log.get_frames().is_empty());
+
+        event_log.set_enabled(true);
+        event_log.set_paused(true);
+        event_system.send(UndescribedEvent);
+        assert!(event_log.get_frames().is_empty());
+
+        event_log.set_paused(false);
+        event_system.send(TestEvent::new("kept"));
+        assert_eq!(event_log.get_frames()[0].events[0].description, "kept");
     }
 }
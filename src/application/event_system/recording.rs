@@ -0,0 +1,556 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        sf_error::{SfError, SfResult},
+        sf_events::*,
+        sf_input::Input,
+        sf_time::FrameClock,
+    },
+    warn_core,
+};
+
+use super::EventSystem;
+
+/// Bumped whenever [`RecordEntry`] or a recorded event changes shape.
+pub const RECORDING_VERSION: u32 = 2;
+
+macro_rules! recorded_events {
+    ($($event:ident),+ $(,)?) => {
+        /// The engine events that can be recorded, named after their type.
+        /// Redraws of the main window are recorded as [`RecordEntry::Frame`] instead. Opened
+        /// windows, which carry the window itself, and events defined by games are left out.
+        /// Raw window events only feed overlays like the GUI, what they captured is recorded
+        /// with each event instead.
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub enum RecordedEvent {
+            $($event($event),)+
+        }
+
+        impl RecordedEvent {
+            /// `None` for events that can't be recorded.
+            pub fn from_event(event: &dyn Eventable) -> Option<Self> {
+                let event = event as &dyn Any;
+                $(
+                    if let Some(event) = event.downcast_ref::<$event>() {
+                        return Some(RecordedEvent::$event(event.clone()));
+                    }
+                )+
+                None
+            }
+
+            pub fn into_event(self) -> Box<dyn Eventable> {
+                match self {
+                    $(RecordedEvent::$event(event) => Box::new(event),)+
+                }
+            }
+        }
+    };
+}
+
+recorded_events!(
+    AppResumedEvent,
+    AppSuspendedEvent,
+    AppAboutToWaitEvent,
+    KeyPressedEvent,
+    KeyReleasedEvent,
+    TextInputEvent,
+    ImeEvent,
+    ModifiersChangedEvent,
+    MouseMoveEvent,
+    MouseButtonPressedEvent,
+    MouseButtonReleasedEvent,
+    MouseScrolledEvent,
+    CursorEnteredEvent,
+    CursorLeftEvent,
+    TouchEvent,
+    WindowCloseEvent,
+    WindowResizeEvent,
+    WindowRedrawRequestedEvent,
+    WindowFocusEvent,
+    WindowMovedEvent,
+    WindowScaleFactorChangedEvent,
+    WindowOccludedEvent,
+    FileDroppedEvent,
+    GamepadConnectedEvent,
+    GamepadDisconnectedEvent,
+    GamepadButtonPressedEvent,
+    GamepadButtonReleasedEvent,
+    GamepadAxisMovedEvent,
+);
+
+/// One line of a recording. `frame` counts the frames recorded before, `time` is measured
+/// from the start of the recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordEntry {
+    Event {
+        frame: u64,
+        time: Duration,
+        event: RecordedEvent,
+        /// The overlay that handled the event, e.g. a click on the GUI, see
+        /// [`EventSystem::on_replayed_event`].
+        captured_by: Option<String>,
+    },
+    /// A frame of the main window that took `delta`.
+    Frame {
+        frame: u64,
+        time: Duration,
+        delta: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordingHeader {
+    version: u32,
+    fixed_step: Duration,
+    max_fixed_steps_per_frame: u32,
+}
+
+/// Writes the events of an [`EventSystem`] to a gzip compressed file, one RON entry per line.
+/// The file is flushed after every frame so a crash loses at most the frame in progress.
+pub struct EventRecorder {
+    path: PathBuf,
+    writer: GzEncoder<BufWriter<File>>,
+    started: Instant,
+    frame: u64,
+}
+
+impl EventRecorder {
+    /// Stores the settings of `frame_clock` so replays split frames into the same fixed updates.
+    pub fn create(path: impl AsRef<Path>, frame_clock: &FrameClock) -> SfResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file =
+            File::create(&path).map_err(|e| SfError::RecordingWriteFailed(path.clone(), e))?;
+        let mut recorder = Self {
+            path,
+            writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            started: Instant::now(),
+            frame: 0,
+        };
+        recorder.write_line(&RecordingHeader {
+            version: RECORDING_VERSION,
+            fixed_step: frame_clock.get_fixed_step(),
+            max_fixed_steps_per_frame: frame_clock.get_max_fixed_steps_per_frame(),
+        })?;
+        Ok(recorder)
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Events that can't be recorded are skipped.
+    pub fn record_event(
+        &mut self,
+        event: &dyn Eventable,
+        captured_by: Option<String>,
+    ) -> SfResult<()> {
+        let Some(event) = RecordedEvent::from_event(event) else {
+            return Ok(());
+        };
+        self.write_line(&RecordEntry::Event {
+            frame: self.frame,
+            time: self.started.elapsed(),
+            event,
+            captured_by,
+        })
+    }
+
+    pub fn record_frame(&mut self, delta: Duration) -> SfResult<()> {
+        self.write_line(&RecordEntry::Frame {
+            frame: self.frame,
+            time: self.started.elapsed(),
+            delta,
+        })?;
+        self.frame += 1;
+        self.writer
+            .flush()
+            .map_err(|e| SfError::RecordingWriteFailed(self.path.clone(), e))
+    }
+
+    /// Completes the file, dropping the recorder does the same but ignores errors.
+    pub fn finish(self) -> SfResult<()> {
+        self.writer
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .map_err(|e| SfError::RecordingWriteFailed(self.path, e))
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> SfResult<()> {
+        let mut line = ron::to_string(value).map_err(SfError::RecordingSerializeFailed)?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .map_err(|e| SfError::RecordingWriteFailed(self.path.clone(), e))
+    }
+}
+
+/// A recording loaded from a file written by [`EventRecorder`].
+#[derive(Debug, Clone)]
+pub struct EventRecording {
+    pub fixed_step: Duration,
+    pub max_fixed_steps_per_frame: u32,
+    pub entries: Vec<RecordEntry>,
+}
+
+impl EventRecording {
+    /// A recording cut off by a crash loads up to the last complete entry.
+    pub fn load(path: impl AsRef<Path>) -> SfResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| SfError::RecordingReadFailed(path.into(), e))?;
+        let mut lines = BufReader::new(GzDecoder::new(BufReader::new(file))).lines();
+
+        let header: RecordingHeader = match lines.next() {
+            Some(Ok(line)) => ron::from_str(&line)
+                .map_err(|e| SfError::RecordingParseFailed(path.into(), 1, e))?,
+            Some(Err(e)) => return Err(SfError::RecordingReadFailed(path.into(), e)),
+            None => {
+                let e = std::io::ErrorKind::UnexpectedEof.into();
+                return Err(SfError::RecordingReadFailed(path.into(), e));
+            }
+        };
+        if header.version != RECORDING_VERSION {
+            return Err(SfError::RecordingVersionUnsupported(
+                path.into(),
+                header.version,
+            ));
+        }
+
+        let mut entries = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    warn_core!("recording {} ends early: {}", path.display(), e);
+                    break;
+                }
+            };
+            let entry = ron::from_str(&line)
+                .map_err(|e| SfError::RecordingParseFailed(path.into(), index + 2, e))?;
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            fixed_step: header.fixed_step,
+            max_fixed_steps_per_frame: header.max_fixed_steps_per_frame,
+            entries,
+        })
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry, RecordEntry::Frame { .. }))
+            .count()
+    }
+}
+
+/// Feeds a recording into an [`EventSystem`] the way the window manager fed the live events:
+/// the input state is updated after each event is dispatched and its per frame state is reset
+/// after each frame. Overlays capture the events they captured live. Frames use the recorded deltas, so fixed and variable updates run exactly as
+/// they did live. Needs no window, so replays can run headlessly, e.g. in tests.
+pub struct EventReplayer {
+    recording: EventRecording,
+    next_entry: usize,
+    input: Option<Rc<RefCell<Input>>>,
+}
+
+impl EventReplayer {
+    pub fn new(recording: EventRecording) -> Self {
+        Self {
+            recording,
+            next_entry: 0,
+            input: None,
+        }
+    }
+
+    /// Keeps `input` up to date like the window manager does.
+    pub fn with_input(mut self, input: Rc<RefCell<Input>>) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_entry >= self.recording.entries.len()
+    }
+
+    /// Replays entries up to and including the next frame, returns `false` once nothing is left.
+    /// The first step resets the frame clock of `event_system` to the recorded settings.
+    pub fn step_frame(&mut self, event_system: &mut EventSystem) -> bool {
+        if self.next_entry == 0 {
+            let mut frame_clock = FrameClock::new(self.recording.fixed_step);
+            frame_clock.set_max_fixed_steps_per_frame(self.recording.max_fixed_steps_per_frame);
+            event_system.frame_clock = frame_clock;
+        }
+
+        while let Some(entry) = self.recording.entries.get(self.next_entry) {
+            self.next_entry += 1;
+            match entry {
+                RecordEntry::Event {
+                    event, captured_by, ..
+                } => {
+                    let mut event = event.clone().into_event();
+                    event_system.on_replayed_event(event.as_mut(), captured_by.as_deref());
+                    if let Some(input) = &self.input {
+                        input.borrow_mut().on_event(event.as_ref());
+                    }
+                }
+                RecordEntry::Frame { delta, .. } => {
+                    event_system.replay_frame(*delta);
+                    if let Some(input) = &self.input {
+                        input.borrow_mut().end_frame();
+                    }
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Replays everything that is left.
+    pub fn run(&mut self, event_system: &mut EventSystem) {
+        while self.step_frame(event_system) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{
+        event::{DeviceId, MouseButton},
+        keyboard::KeyCode,
+        window::WindowId,
+    };
+
+    use super::*;
+    use crate::core::test_utils::{Log, LoggingLayer};
+
+    /// The layer logs its updates with their timing, the events it gets and whether space is held.
+    fn event_system_with_logging_layer<'a>() -> (EventSystem<'a>, Rc<RefCell<Input>>, Log) {
+        let log = Log::default();
+        let input = Rc::new(RefCell::new(Input::new()));
+        let mut event_system = EventSystem::new();
+        event_system.main_window = Some(WindowId::from(0));
+        event_system.frame_clock = FrameClock::new(Duration::from_millis(1));
+        let (update_log, update_input) = (log.clone(), input.clone());
+        event_system.layer_stack.push_layer(Box::new(LoggingLayer {
+            logs_updates: true,
+            on_update: Some(Box::new(move |_| {
+                let is_space_down = update_input.borrow().is_key_down(KeyCode::Space);
+                update_log
+                    .borrow_mut()
+                    .push(format!("space: {}", is_space_down));
+            })),
+            ..LoggingLayer::new("logging", &log)
+        }));
+        (event_system, input, log)
+    }
+
+    fn key_pressed() -> KeyPressedEvent {
+        KeyPressedEvent {
+            name: String::from("KEY PRESSED EVENT"),
+            window_id: WindowId::from(0),
+            keycode: KeyCode::Space,
+            repeat: false,
+            is_handled: false,
+        }
+    }
+
+    fn mouse_pressed() -> MouseButtonPressedEvent {
+        MouseButtonPressedEvent {
+            name: String::from("MOUSE BUTTON PRESSED EVENT"),
+            is_handled: false,
+            window_id: WindowId::from(0),
+            device_id: DeviceId::dummy(),
+            button: MouseButton::Left,
+        }
+    }
+
+    fn key_released() -> KeyReleasedEvent {
+        KeyReleasedEvent {
+            name: String::from("KEY RELEASED EVENT"),
+            window_id: WindowId::from(0),
+            keycode: KeyCode::Space,
+            is_handled: false,
+        }
+    }
+
+    fn redraw() -> WindowRedrawRequestedEvent {
+        WindowRedrawRequestedEvent {
+            name: String::from("WINDOW REDRAW REQUESTED EVENT"),
+            window_id: WindowId::from(0),
+            is_handled: false,
+        }
+    }
+
+    /// What the window manager does with an event coming from winit.
+    fn live_event(
+        event_system: &mut EventSystem,
+        input: &RefCell<Input>,
+        mut event: impl Eventable,
+    ) {
+        event_system.on_dynamic_event(&mut event);
+        input.borrow_mut().on_event(&event);
+    }
+
+    /// A main window redraw that took `delta`, the live frame clock would measure it.
+    fn live_frame(event_system: &mut EventSystem, input: &RefCell<Input>, delta: Duration) {
+        event_system.replay_frame(delta);
+        input.borrow_mut().end_frame();
+    }
+
+    #[test]
+    fn replay_drives_layers_like_the_live_session() {
+        let path = std::env::temp_dir().join(format!("strife_recording_{}.gz", std::process::id()));
+
+        let (mut event_system, input, live_log) = event_system_with_logging_layer();
+        event_system.start_recording(&path).unwrap();
+        live_frame(&mut event_system, &input, Duration::from_millis(1));
+        live_event(&mut event_system, &input, key_pressed());
+        // A preview window, layers render it while handling the redraw.
+        live_event(
+            &mut event_system,
+            &input,
+            WindowRedrawRequestedEvent {
+                window_id: WindowId::from(1),
+                ..redraw()
+            },
+        );
+        live_frame(&mut event_system, &input, Duration::from_millis(3));
+        live_event(&mut event_system, &input, key_released());
+        live_frame(&mut event_system, &input, Duration::from_millis(2));
+        event_system.stop_recording().unwrap();
+
+        let recording = EventRecording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.get_frame_count(), 3);
+
+        let (mut event_system, input, replay_log) = event_system_with_logging_layer();
+        event_system.frame_clock = FrameClock::default();
+        let mut replayer = EventReplayer::new(recording).with_input(input);
+        replayer.run(&mut event_system);
+
+        assert!(replayer.is_finished());
+        assert!(
+            live_log
+                .borrow()
+                .iter()
+                .any(|line| line.contains("space: true"))
+        );
+        assert!(live_log.borrow().iter().any(|line| line.contains("REDRAW")));
+        assert_eq!(
+            live_log
+                .borrow()
+                .iter()
+                .filter(|line| line.contains("fixed update"))
+                .count(),
+            6
+        );
+        assert_eq!(*replay_log.borrow(), *live_log.borrow());
+    }
+
+    #[test]
+    fn replay_applies_what_the_gui_captured_live() {
+        let path = std::env::temp_dir().join(format!("strife_captured_{}.gz", std::process::id()));
+        // Captures the events of `categories`, as the GUI does when the cursor is over a panel.
+        let push_gui = |event_system: &mut EventSystem, log: &Log, categories| {
+            event_system
+                .layer_stack
+                .push_overlay(Box::new(LoggingLayer {
+                    categories,
+                    handles_events: true,
+                    ..LoggingLayer::new("gui", log)
+                }));
+        };
+
+        let (mut event_system, input, live_log) = event_system_with_logging_layer();
+        push_gui(
+            &mut event_system,
+            &live_log,
+            EventCategory::MouseCategory.into(),
+        );
+        event_system.start_recording(&path).unwrap();
+        live_event(&mut event_system, &input, mouse_pressed());
+        live_event(&mut event_system, &input, key_pressed());
+        live_frame(&mut event_system, &input, Duration::from_millis(1));
+        event_system.stop_recording().unwrap();
+        let live_input = input;
+
+        let recording = EventRecording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // Without the window's raw events the GUI decides differently on replay.
+        let (mut event_system, input, replay_log) = event_system_with_logging_layer();
+        push_gui(&mut event_system, &replay_log, EventCategoryFlags::ALL);
+        EventReplayer::new(recording)
+            .with_input(input.clone())
+            .run(&mut event_system);
+
+        let game_lines = |log: &Log| -> Vec<String> {
+            log.borrow()
+                .iter()
+                .filter(|line| !line.starts_with("gui"))
+                .cloned()
+                .collect()
+        };
+        assert!(
+            !game_lines(&live_log)
+                .iter()
+                .any(|line| line.contains("MOUSE"))
+        );
+        assert_eq!(game_lines(&replay_log), game_lines(&live_log));
+        assert!(!live_input.borrow().is_mouse_button_down(MouseButton::Left));
+        assert!(!input.borrow().is_mouse_button_down(MouseButton::Left));
+        assert!(input.borrow().is_key_down(KeyCode::Space));
+    }
+
+    #[test]
+    fn events_sent_by_game_code_are_not_recorded() {
+        let path = std::env::temp_dir().join(format!("strife_sent_{}.gz", std::process::id()));
+
+        let (mut event_system, _input, _log) = event_system_with_logging_layer();
+        event_system.start_recording(&path).unwrap();
+        event_system.on_event(key_pressed());
+        event_system.send(key_released());
+        event_system.send_boxed(Box::new(key_pressed()));
+        event_system.get_event_queue().send(key_released());
+        event_system.on_event(redraw());
+        event_system.stop_recording().unwrap();
+
+        let recording = EventRecording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let recorded: Vec<&str> = recording
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                RecordEntry::Event { event, .. } => {
+                    Some(event.clone().into_event().get_type_name())
+                }
+                RecordEntry::Frame { .. } => None,
+            })
+            .collect();
+        assert_eq!(recorded, [key_pressed().get_type_name()]);
+        assert_eq!(recording.get_frame_count(), 1);
+    }
+
+    #[test]
+    fn engine_events_serialize_under_their_type_name() {
+        let event = key_pressed();
+        let recorded = ron::to_string(&RecordedEvent::from_event(&event).unwrap()).unwrap();
+        assert!(recorded.starts_with(&format!("{}(", event.get_type_name())));
+
+        let data = event.to_ron().unwrap();
+        let parsed: KeyPressedEvent = ron::from_str(&data).unwrap();
+        assert_eq!(parsed.keycode, KeyCode::Space);
+        assert_eq!(parsed.window_id, event.window_id);
+    }
+}
@@ -1,7 +1,13 @@
+use std::{cell::RefCell, rc::Rc};
+
 use crate::{
     core::{
-        sf_events::{EventDispatcher, EventListener, Eventable, MouseMoveEvent},
+        sf_events::{
+            EventCategory, EventCategoryFlags, EventDispatcher, Eventable, MouseMoveEvent,
+        },
+        sf_input::Input,
         sf_layers,
+        sf_time::Timestep,
     },
     info_client, info_core,
 };
@@ -9,40 +15,30 @@ use crate::{
 pub struct TestLayer<'a> {
     pub name: String,
     pub event_dispatcher: EventDispatcher<'a>,
-    pub mouse_x: f64,
-    pub mouse_y: f64,
-}
-
-struct TestMouseMoveListenerWithLayerName {
-    pub layer_name: String,
-}
-impl EventListener for TestMouseMoveListenerWithLayerName {
-    type EventableConcreteType = MouseMoveEvent;
-
-    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
-        info_client!(
-            "{}",
-            format!(
-                "MOUSE MOVE FROM {} : x- {}, y -{}",
-                self.layer_name, event.x, event.y
-            )
-        );
-        true
-    }
+    pub input: Rc<RefCell<Input>>,
 }
 
 impl<'a> TestLayer<'a> {
-    pub fn new(name: &String) -> Self {
+    pub fn new(name: &str, input: Rc<RefCell<Input>>) -> Self {
         let mut event_dispatcher = EventDispatcher::new();
-        event_dispatcher.add_listener(TestMouseMoveListenerWithLayerName {
-            layer_name: name.clone(),
-        });
+        let layer_name = name.to_string();
+        event_dispatcher
+            .on::<MouseMoveEvent>(move |event| {
+                info_client!(
+                    "{}",
+                    format!(
+                        "MOUSE MOVE FROM {} : x- {}, y -{}",
+                        layer_name, event.x, event.y
+                    )
+                );
+                true
+            })
+            .forget();
 
         Self {
-            name: name.clone(),
+            name: name.to_string(),
             event_dispatcher,
-            mouse_y: -1.0,
-            mouse_x: -1.0,
+            input,
         }
     }
 }
@@ -56,10 +52,18 @@ impl<'a> sf_layers::Layer for TestLayer<'a> {
 
     fn on_detach(&mut self) {}
 
-    fn on_update(&mut self) {
-        info_core!("TEST LAYER ON UPDATE");
+    fn on_update(&mut self, _timestep: Timestep) {
+        let input = self.input.borrow();
+        info_core!(
+            "TEST LAYER ON UPDATE: mouse - {:?}, delta - {:?}",
+            input.get_mouse_position(),
+            input.get_mouse_delta()
+        );
     }
-    fn on_event(&mut self, event: &dyn Eventable) {
+    fn on_event(&mut self, event: &mut dyn Eventable) {
         self.event_dispatcher.dispatch_dynamic(event);
     }
+    fn get_event_categories(&self) -> EventCategoryFlags {
+        EventCategory::MouseCategory.into()
+    }
 }
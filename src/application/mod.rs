@@ -1,46 +1,142 @@
 use std::{
-    cell::{Ref, RefCell},
-    ops::Deref,
+    cell::RefCell,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
 };
 
 use event_system::EventSystem;
-use layers::TestLayer;
-use wgpu::rwh::HasWindowHandle;
+use glam::{Quat, Vec3};
 use winit::{
-    dpi::{LogicalSize, PhysicalSize},
+    dpi::LogicalSize,
     event_loop::{EventLoop, EventLoopProxy},
-    raw_window_handle_05,
-    window::{Window, WindowAttributes},
+    window::{Window, WindowId},
 };
 
 use crate::{
     core::{
-        sf_events::{EventListener, Eventable, MouseMoveEvent},
-        sf_graphics,
-        sf_gui::{self, SfGuiLayerWrapper},
-        sf_layers::Layer,
-    },
-    sf_window::{
-        self, RawWindowHandleWrapper, WindowManager, WindowManagerCustomEvent, WindowWrapper,
+        sf_assets::{get_resource_path, get_user_config_directory},
+        sf_error::{SfError, SfResult},
+        sf_events::{EventListener, Eventable, MouseMoveEvent, Subscription},
+        sf_graphics::{material::MaterialDescriptor, texture::Texture, wgpu_backend::WgpuGraphics},
+        sf_gui::SfGuiLayerWrapper,
+        sf_input::{Input, InputMap},
+        sf_time::Timestep,
+        world::{Camera, Mesh, Transform, Vertex, World, WorldLayerWrapper},
     },
+    error_core,
+    sf_window::{self, WindowManagerCustomEvent, Windows},
+    warn_core,
 };
 
 pub mod event_system;
 pub mod layers;
+pub mod window_surfaces;
 
 struct EventListenerForWindow<'a> {
-    event_sys: Arc<RefCell<EventSystem<'a>>>,
+    event_sys: Rc<RefCell<EventSystem<'a>>>,
+    event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
+    /// Created on the first resume, when the main window exists.
+    graphics: Option<Rc<RefCell<WgpuGraphics>>>,
+    input: Rc<RefCell<Input>>,
+    surface_subscriptions: Vec<Subscription>,
+}
+
+impl<'a> EventListenerForWindow<'a> {
+    /// Sets up graphics, the world and the layers for the freshly created main window.
+    fn start(&mut self, main_window: &Arc<Window>) -> SfResult<Rc<RefCell<WgpuGraphics>>> {
+        let inner_size = main_window.inner_size();
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let surface = instance.create_surface(main_window.clone())?;
+
+        let graphics = Rc::new(RefCell::new(pollster::block_on(WgpuGraphics::new(
+            instance,
+            main_window.id(),
+            surface,
+            inner_size,
+        ))?));
+
+        let mut event_system = self.event_sys.borrow_mut();
+        event_system.main_window = Some(main_window.id());
+        self.surface_subscriptions = window_surfaces::add_window_surface_listeners(
+            &mut event_system.non_layer_event_dispatcher,
+            graphics.clone(),
+        );
+
+        let world = Rc::new(RefCell::new(World::new(graphics.clone())));
+        spawn_test_scene(
+            &mut world.borrow_mut(),
+            &graphics.borrow(),
+            self.input.clone(),
+        )?;
+
+        event_system
+            .layer_stack
+            .push_layer(Box::new(WorldLayerWrapper::new(
+                "world".to_string(),
+                world.clone(),
+                graphics.clone(),
+            )));
+
+        let sf_gui_layer = Box::new(SfGuiLayerWrapper::new(
+            "hello".to_string(),
+            main_window.clone(),
+            graphics.clone(),
+            world,
+            self.event_loop_proxy.clone(),
+            event_system.get_event_log(),
+        ));
+        event_system.layer_stack.push_overlay(sf_gui_layer);
+
+        Ok(graphics)
+    }
 }
 
 impl<'a> sf_window::WindowEventListener for EventListenerForWindow<'a> {
-    fn on_handled_event<T: Eventable>(&mut self, event: T) {
-        self.event_sys.borrow_mut().on_event(event);
+    fn on_handled_event<T: Eventable>(&mut self, event: &mut T) {
+        self.event_sys.borrow_mut().on_dynamic_event(event);
+    }
+
+    fn on_sent_event(&mut self, event: Box<dyn Eventable>) {
+        self.event_sys.borrow_mut().send_boxed(event);
     }
 
-    fn on_raw_window_event(&mut self, event: winit::event::WindowEvent) {
-        self.event_sys.borrow_mut().on_event(event);
+    /// Raw events only feed the GUI of the main window.
+    fn on_raw_window_event(&mut self, window_id: WindowId, event: winit::event::WindowEvent) {
+        let mut event_sys = self.event_sys.borrow_mut();
+        if event_sys.main_window == Some(window_id) {
+            event_sys.on_event(event);
+        }
+    }
+
+    /// The engine starts on the first resume, later resumes recreate the surfaces dropped on suspend.
+    fn on_resumed(&mut self, windows: &Windows) -> SfResult<()> {
+        let graphics = match &self.graphics {
+            Some(graphics) => graphics.clone(),
+            None => {
+                let Some(main_window) = windows.get_main_window() else {
+                    return Ok(());
+                };
+                let graphics = self.start(main_window)?;
+                self.graphics = Some(graphics.clone());
+                graphics
+            }
+        };
+
+        let mut graphics = graphics.borrow_mut();
+        for window in windows.iter() {
+            if graphics.get_surface(window.id()).is_none() {
+                let surface = graphics.instance.create_surface(window.clone())?;
+                graphics.add_surface(window.id(), surface, window.inner_size())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_suspended(&mut self, _windows: &Windows) {
+        if let Some(graphics) = &self.graphics {
+            graphics.borrow_mut().clear_surfaces();
+        }
     }
 }
 
@@ -48,63 +144,218 @@ struct MouseMoveListener;
 impl EventListener for MouseMoveListener {
     type EventableConcreteType = MouseMoveEvent;
 
-    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
+    fn handle(&mut self, _event: &Self::EventableConcreteType) -> bool {
         false
     }
 }
 
-pub async fn run() {
-    let mut event_system = Arc::new(RefCell::new(EventSystem::new()));
+struct Spin {
+    radians_per_second: f32,
+}
 
-    let event_loop = EventLoop::<WindowManagerCustomEvent>::with_user_event()
-        .build()
-        .unwrap();
+fn spawn_test_scene(
+    world: &mut World,
+    graphics: &WgpuGraphics,
+    input: Rc<RefCell<Input>>,
+) -> SfResult<()> {
+    let camera = world.spawn();
+    world.insert(camera, Transform::from_position(Vec3::new(0.0, 0.0, 2.0)));
+    world.insert(
+        camera,
+        Camera::perspective(std::f32::consts::FRAC_PI_4, 0.1, 100.0),
+    );
 
-    let event_loop_proxy = event_loop.create_proxy();
+    let triangle = Mesh::new(
+        &graphics.device,
+        &[
+            Vertex {
+                tex_coords: [0.5, 0.0],
+                ..Vertex::from_position([0.0, 0.5, 0.0])
+            },
+            Vertex {
+                tex_coords: [0.0, 1.0],
+                ..Vertex::from_position([-0.5, -0.5, 0.0])
+            },
+            Vertex {
+                tex_coords: [1.0, 1.0],
+                ..Vertex::from_position([0.5, -0.5, 0.0])
+            },
+        ],
+        &[0, 1, 2],
+    );
+
+    let checker = image::RgbaImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            image::Rgba([230, 230, 230, 255])
+        } else {
+            image::Rgba([40, 40, 40, 255])
+        }
+    });
+    let checker = Rc::new(Texture::from_image(
+        &graphics.device,
+        &graphics.queue,
+        &image::DynamicImage::ImageRgba8(checker),
+        Some("Checker texture"),
+    )?);
+    let checker = world.create_material(
+        &graphics.device,
+        MaterialDescriptor {
+            texture: Some(checker),
+            ..MaterialDescriptor::new(world.standard_shader)
+        },
+    );
 
-    let window = Arc::new(event_loop.create_window(WindowAttributes::new()).unwrap());
-    window.set_min_inner_size(Some(LogicalSize::new(100, 100)));
+    for (i, x) in [-0.6, 0.0, 0.6].into_iter().enumerate() {
+        let entity = world.spawn();
+        world.insert(
+            entity,
+            Transform::from_position(Vec3::new(x, 0.0, 0.0)).with_scale(Vec3::splat(0.4)),
+        );
+        world.insert(entity, triangle.clone());
+        world.insert(entity, checker.clone());
+        world.insert(
+            entity,
+            Spin {
+                radians_per_second: 0.5 * (i + 1) as f32,
+            },
+        );
+    }
 
-    let window_wrapper = WindowWrapper::new(window.clone());
-    let inner_size = window.inner_size().clone();
-    let raw_window_handle_wrapper = RawWindowHandleWrapper::new(&window_wrapper).unwrap();
+    world.add_system(|world: &mut World, timestep: Timestep| {
+        for (_, (transform, spin)) in world.query_mut::<(&mut Transform, &Spin)>() {
+            transform.rotation *=
+                Quat::from_rotation_z(spin.radians_per_second * timestep.get_seconds());
+        }
+    });
+    world.add_system(move |world: &mut World, timestep: Timestep| {
+        let input = input.borrow();
+        let direction = input.get_axis_2d("move").extend(0.0);
+        let zoom = input.get_scroll_delta().1 as f32 * 0.2;
+        let reset = input.is_action_just_pressed("reset_camera");
 
-    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
-    let surface = instance
-        .create_surface(raw_window_handle_wrapper.get_handle())
-        .unwrap();
+        for (_, (transform, _)) in world.query_mut::<(&mut Transform, &Camera)>() {
+            if reset {
+                transform.position = Vec3::new(0.0, 0.0, 2.0);
+            }
+            transform.position += direction * timestep.get_seconds();
+            transform.position.z = (transform.position.z - zoom).max(0.5);
+        }
+    });
 
-    let mut window_manager = sf_window::WindowManager::<EventListenerForWindow>::new(
-        None,
-        event_loop,
-        event_loop_proxy,
-        window.clone(),
-    );
+    Ok(())
+}
 
-    event_system
+/// Default bindings shipped with the game, inside the resource directory.
+pub const DEFAULT_INPUT_MAP_FILE: &str = "input.ron";
+/// Bindings the player changed, inside the user's config directory.
+pub const PLAYER_INPUT_MAP_FILE: &str = "bindings.ron";
+
+pub fn get_player_input_map_path() -> Option<PathBuf> {
+    get_user_config_directory().map(|directory| directory.join(PLAYER_INPUT_MAP_FILE))
+}
+
+/// The default bindings built into the executable, used when the resource directory has no readable ones.
+const BUILT_IN_INPUT_MAP: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/input.ron"));
+
+fn get_built_in_input_map() -> InputMap {
+    ron::from_str(BUILT_IN_INPUT_MAP).expect("built in input map is valid")
+}
+
+fn load_default_input_map(path: &Path) -> InputMap {
+    InputMap::load(path).unwrap_or_else(|e| {
+        warn_core!("{}, using the built in bindings", e);
+        get_built_in_input_map()
+    })
+}
+
+/// The player's bindings on top of the defaults. Actions added since the player saved get their
+/// default bindings.
+fn load_input_map(defaults_path: &Path, player_path: Option<&Path>) -> InputMap {
+    let defaults = load_default_input_map(defaults_path);
+    let Some(player_path) = player_path.filter(|path| path.exists()) else {
+        return defaults;
+    };
+
+    match InputMap::load(player_path) {
+        Ok(mut input_map) => {
+            input_map.extend_missing(&defaults);
+            input_map
+        }
+        Err(e) => {
+            error_core!("{}, using default bindings", e);
+            defaults
+        }
+    }
+}
+
+/// Saves rebound bindings for the current user, the defaults in the resource directory stay untouched.
+pub fn save_player_input_map(input_map: &InputMap) -> SfResult<()> {
+    let path = get_player_input_map_path().ok_or(SfError::UserConfigDirectoryUnavailable)?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)
+            .map_err(|e| SfError::InputMapWriteFailed(path.clone(), e))?;
+    }
+    input_map.save(path)
+}
+
+/// Set to a file path to record the session's events, see [`event_system::EventReplayer`].
+pub const RECORD_EVENTS_ENV_VAR: &str = "STRIFE_RECORD_EVENTS";
+
+pub fn run() -> SfResult<()> {
+    let event_system = Rc::new(RefCell::new(EventSystem::new()));
+    if let Ok(path) = std::env::var(RECORD_EVENTS_ENV_VAR)
+        && let Err(e) = event_system.borrow_mut().start_recording(&path)
+    {
+        error_core!("{}", e);
+    }
+    let _mouse_move_subscription = event_system
         .borrow_mut()
         .non_layer_event_dispatcher
         .add_listener(MouseMoveListener);
 
-    let graphics = Rc::new(RefCell::new(
-        sf_graphics::wgpu_backend::WgpuGraphics::new(instance, surface, inner_size).await,
-    ));
+    let event_loop = EventLoop::<WindowManagerCustomEvent>::with_user_event().build()?;
+    let event_loop_proxy = event_loop.create_proxy();
 
-    let mut sf_gui_layer = Box::new(SfGuiLayerWrapper::new(
-        "hello".to_string(),
-        window.clone(),
-        graphics.clone(),
-    ));
+    let main_window_attributes =
+        Window::default_attributes().with_min_inner_size(LogicalSize::new(100, 100));
+    let mut window_manager = sf_window::WindowManager::<EventListenerForWindow>::new(
+        None,
+        event_loop_proxy.clone(),
+        main_window_attributes,
+    );
 
-    sf_gui_layer.get_name();
-    event_system
+    window_manager
+        .get_input()
         .borrow_mut()
-        .layer_stack
-        .push_layer(sf_gui_layer);
+        .set_input_map(load_input_map(
+            &get_resource_path(DEFAULT_INPUT_MAP_FILE),
+            get_player_input_map_path().as_deref(),
+        ));
 
     let event_listener_for_window = EventListenerForWindow {
         event_sys: event_system.clone(),
+        event_loop_proxy,
+        graphics: None,
+        input: window_manager.get_input(),
+        surface_subscriptions: Vec::new(),
     };
     window_manager.set_event_listener(Some(event_listener_for_window));
-    window_manager.run();
+    window_manager.run(event_loop)?;
+    event_system.borrow_mut().stop_recording()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_default_bindings_fall_back_to_the_shipped_file() {
+        let shipped = InputMap::load(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/input.ron"));
+
+        let input_map = load_input_map(Path::new("missing/input.ron"), None);
+
+        assert_eq!(input_map, shipped.unwrap());
+        assert!(!input_map.get_action_bindings("reset_camera").is_empty());
+    }
 }
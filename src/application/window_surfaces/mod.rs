@@ -0,0 +1,90 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    core::{
+        sf_events::{
+            EventDispatcher, EventListener, Subscription, WindowCloseEvent, WindowOpenedEvent,
+            WindowResizeEvent,
+        },
+        sf_graphics::wgpu_backend::WgpuGraphics,
+    },
+    error_core,
+};
+
+/// Keeps a surface in `graphics` for every secondary window. The main window surface is managed
+/// by whoever renders into it. The listeners are removed when the returned subscriptions are dropped.
+pub fn add_window_surface_listeners(
+    dispatcher: &mut EventDispatcher,
+    graphics: Rc<RefCell<WgpuGraphics>>,
+) -> Vec<Subscription> {
+    vec![
+        dispatcher.add_listener(WindowOpenedSurfaceListener {
+            graphics: graphics.clone(),
+        }),
+        dispatcher.add_listener(WindowClosedSurfaceListener {
+            graphics: graphics.clone(),
+        }),
+        dispatcher.add_listener(WindowResizedSurfaceListener { graphics }),
+    ]
+}
+
+struct WindowOpenedSurfaceListener {
+    graphics: Rc<RefCell<WgpuGraphics>>,
+}
+impl EventListener for WindowOpenedSurfaceListener {
+    type EventableConcreteType = WindowOpenedEvent;
+
+    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
+        let surface = self
+            .graphics
+            .borrow()
+            .instance
+            .create_surface(event.window.clone());
+
+        let result = surface.map_err(Into::into).and_then(|surface| {
+            self.graphics.borrow_mut().add_surface(
+                event.get_window_id(),
+                surface,
+                event.window.inner_size(),
+            )
+        });
+        if let Err(e) = result {
+            error_core!("no surface for window {}: {}", event.get_label(), e);
+        }
+        false
+    }
+}
+
+struct WindowClosedSurfaceListener {
+    graphics: Rc<RefCell<WgpuGraphics>>,
+}
+impl EventListener for WindowClosedSurfaceListener {
+    type EventableConcreteType = WindowCloseEvent;
+
+    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
+        let mut graphics = self.graphics.borrow_mut();
+        if graphics.main_window != Some(event.get_window_id()) {
+            graphics.remove_surface(event.get_window_id());
+        }
+        false
+    }
+}
+
+struct WindowResizedSurfaceListener {
+    graphics: Rc<RefCell<WgpuGraphics>>,
+}
+impl EventListener for WindowResizedSurfaceListener {
+    type EventableConcreteType = WindowResizeEvent;
+
+    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
+        let mut graphics = self.graphics.borrow_mut();
+        if graphics.main_window != Some(event.get_window_id()) {
+            let (width, height) = event.get_width_and_height();
+            graphics.resize_surface(
+                event.get_window_id(),
+                winit::dpi::PhysicalSize { width, height },
+            );
+        }
+        false
+    }
+}
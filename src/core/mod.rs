@@ -1,5 +1,12 @@
+pub mod sf_assets;
+pub mod sf_error;
 pub mod sf_events;
 pub mod sf_graphics;
 pub mod sf_gui;
+pub mod sf_input;
 pub mod sf_layers;
+pub mod sf_time;
 pub mod world;
+
+#[cfg(test)]
+pub mod test_utils;
@@ -0,0 +1,271 @@
+use std::path::Path;
+
+use glam::{Mat3, Mat4, Vec3};
+
+use crate::{
+    core::{
+        sf_error::{SfError, SfResult},
+        world::{Mesh, MeshData, Vertex},
+    },
+    warn_core,
+};
+
+/// Loads a mesh file and uploads it, see [`load_mesh_data`] for the supported formats.
+pub fn load_mesh(device: &wgpu::Device, path: impl AsRef<Path>) -> SfResult<Mesh> {
+    let data = load_mesh_data(path)?;
+    Ok(Mesh::from_data(device, &data))
+}
+
+/// Loads `.obj`, `.gltf` or `.glb` geometry. Every OBJ object and every glTF primitive becomes a submesh.
+pub fn load_mesh_data(path: impl AsRef<Path>) -> SfResult<MeshData> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    let data = match extension.as_deref() {
+        Some("obj") => load_obj(path)?,
+        Some("gltf") | Some("glb") => load_gltf(path)?,
+        _ => return Err(SfError::UnsupportedAssetFormat(path.to_path_buf())),
+    };
+
+    if data.is_empty() {
+        return Err(SfError::EmptyMesh(path.to_path_buf()));
+    }
+    Ok(data)
+}
+
+pub fn load_obj(path: &Path) -> SfResult<MeshData> {
+    let (models, _materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)
+        .map_err(|e| SfError::ObjLoadFailed(path.to_path_buf(), e))?;
+
+    let mut data = MeshData::default();
+    for model in models {
+        let mesh = &model.mesh;
+        let vertex_count = mesh.positions.len() / 3;
+        let has_normals = mesh.normals.len() == vertex_count * 3;
+        let has_tex_coords = mesh.texcoords.len() == vertex_count * 2;
+
+        let mut vertices: Vec<Vertex> = (0..vertex_count)
+            .map(|i| Vertex {
+                position: [
+                    mesh.positions[i * 3],
+                    mesh.positions[i * 3 + 1],
+                    mesh.positions[i * 3 + 2],
+                ],
+                normal: if has_normals {
+                    [
+                        mesh.normals[i * 3],
+                        mesh.normals[i * 3 + 1],
+                        mesh.normals[i * 3 + 2],
+                    ]
+                } else {
+                    [0.0, 0.0, 0.0]
+                },
+                // OBJ puts the UV origin in the bottom left corner, wgpu in the top left.
+                tex_coords: if has_tex_coords {
+                    [mesh.texcoords[i * 2], 1.0 - mesh.texcoords[i * 2 + 1]]
+                } else {
+                    [0.0, 0.0]
+                },
+            })
+            .collect();
+
+        check_indices(path, &model.name, &mesh.indices, vertices.len())?;
+        if !has_normals {
+            MeshData::compute_normals(&mut vertices, &mesh.indices);
+        }
+
+        data.push_submesh(model.name, &vertices, &mesh.indices, mesh.material_id);
+    }
+
+    Ok(data)
+}
+
+/// Loads every mesh referenced by the scenes of a glTF 2.0 file, baking node transforms into the vertices.
+/// Files without scenes contribute all of their meshes untransformed.
+pub fn load_gltf(path: &Path) -> SfResult<MeshData> {
+    let (document, buffers, _images) =
+        gltf::import(path).map_err(|e| SfError::GltfLoadFailed(path.to_path_buf(), e))?;
+
+    let mut data = MeshData::default();
+    if document.scenes().len() == 0 {
+        for mesh in document.meshes() {
+            append_gltf_mesh(path, &mut data, &mesh, Mat4::IDENTITY, &buffers)?;
+        }
+    }
+    for scene in document.scenes() {
+        for node in scene.nodes() {
+            append_gltf_node(path, &mut data, &node, Mat4::IDENTITY, &buffers)?;
+        }
+    }
+
+    Ok(data)
+}
+
+fn append_gltf_node(
+    path: &Path,
+    data: &mut MeshData,
+    node: &gltf::Node,
+    parent_transform: Mat4,
+    buffers: &[gltf::buffer::Data],
+) -> SfResult<()> {
+    let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        append_gltf_mesh(path, data, &mesh, transform, buffers)?;
+    }
+    for child in node.children() {
+        append_gltf_node(path, data, &child, transform, buffers)?;
+    }
+    Ok(())
+}
+
+fn append_gltf_mesh(
+    path: &Path,
+    data: &mut MeshData,
+    mesh: &gltf::Mesh,
+    transform: Mat4,
+    buffers: &[gltf::buffer::Data],
+) -> SfResult<()> {
+    let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+    // Mirroring transforms turn the triangles inside out, which back face culling would hide.
+    let flips_winding = transform.determinant() < 0.0;
+
+    for primitive in mesh.primitives() {
+        let name = format!("{}#{}", mesh.name().unwrap_or("mesh"), primitive.index());
+
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            warn_core!(
+                "skipping glTF primitive {}, only triangle lists are supported",
+                name
+            );
+            continue;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            warn_core!("skipping glTF primitive {} without positions", name);
+            continue;
+        };
+
+        let mut vertices: Vec<Vertex> = positions
+            .map(|position| Vertex {
+                position: transform.transform_point3(Vec3::from(position)).to_array(),
+                normal: [0.0, 0.0, 0.0],
+                tex_coords: [0.0, 0.0],
+            })
+            .collect();
+
+        if let Some(tex_coords) = reader.read_tex_coords(0) {
+            for (vertex, tex_coords) in vertices.iter_mut().zip(tex_coords.into_f32()) {
+                vertex.tex_coords = tex_coords;
+            }
+        }
+
+        let mut indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        if flips_winding {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        check_indices(path, &name, &indices, vertices.len())?;
+        match reader.read_normals() {
+            Some(normals) => {
+                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                    vertex.normal = (normal_matrix * Vec3::from(normal))
+                        .normalize_or(Vec3::Z)
+                        .to_array();
+                }
+            }
+            None => MeshData::compute_normals(&mut vertices, &indices),
+        }
+
+        data.push_submesh(name, &vertices, &indices, primitive.material().index());
+    }
+    Ok(())
+}
+
+/// Files can reference vertices that don't exist, which would read out of bounds later on.
+fn check_indices(path: &Path, submesh: &str, indices: &[u32], vertex_count: usize) -> SfResult<()> {
+    match indices
+        .iter()
+        .find(|&&index| index as usize >= vertex_count)
+    {
+        Some(&index) => Err(SfError::MeshIndexOutOfBounds(
+            path.to_path_buf(),
+            submesh.to_string(),
+            index,
+            vertex_count,
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/meshes")
+            .join(name)
+    }
+
+    #[test]
+    fn loads_obj_computing_missing_normals() {
+        let data = load_mesh_data(fixture("triangle.obj")).unwrap();
+
+        assert_eq!(data.vertices.len(), 3);
+        assert_eq!(data.indices, [0, 1, 2]);
+        assert_eq!(data.submeshes.len(), 1);
+        assert_eq!(data.submeshes[0].name, "triangle");
+        assert_eq!(data.vertices[0].normal, [0.0, 0.0, 1.0]);
+        // Flipped to wgpu's top left UV origin.
+        assert_eq!(data.vertices[2].tex_coords, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn loads_gltf_baking_node_transforms() {
+        let data = load_mesh_data(fixture("triangle.gltf")).unwrap();
+
+        assert_eq!(data.indices, [0, 1, 2]);
+        assert_eq!(data.submeshes[0].name, "triangle#0");
+        assert_eq!(data.vertices[1].position, [1.0, 0.0, 2.0]);
+        assert_eq!(data.vertices[1].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_indices() {
+        let error = load_mesh_data(fixture("bad_index.gltf")).unwrap_err();
+        assert!(
+            matches!(error, SfError::MeshIndexOutOfBounds(_, _, 5, 3)),
+            "{}",
+            error
+        );
+
+        // tobj catches these itself.
+        assert!(matches!(
+            load_mesh_data(fixture("bad_index.obj")),
+            Err(SfError::ObjLoadFailed(
+                _,
+                tobj::LoadError::FaceVertexOutOfBounds
+            ))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_extensions() {
+        assert!(matches!(
+            load_mesh_data(fixture("triangle.txt")),
+            Err(SfError::UnsupportedAssetFormat(_))
+        ));
+    }
+}
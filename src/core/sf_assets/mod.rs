@@ -0,0 +1,4 @@
+pub mod mesh_loader;
+pub use mesh_loader::*;
+pub mod resources;
+pub use resources::*;
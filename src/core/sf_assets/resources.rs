@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+/// Environment variable that overrides where [`get_resource_directory`] looks.
+pub const RESOURCE_DIRECTORY_VAR: &str = "STRIFE_RESOURCES";
+
+/// Directory the engine loads its runtime resources from, e.g. shaders and the default input bindings.
+/// Taken from `STRIFE_RESOURCES` if set, otherwise the first `resources` directory found next to
+/// the executable or in the working directory.
+pub fn get_resource_directory() -> PathBuf {
+    if let Some(directory) = std::env::var_os(RESOURCE_DIRECTORY_VAR) {
+        return PathBuf::from(directory);
+    }
+
+    let executable_directory = std::env::current_exe()
+        .ok()
+        .and_then(|executable| executable.parent().map(Path::to_path_buf));
+    executable_directory
+        .into_iter()
+        .chain(std::env::current_dir().ok())
+        .map(|directory| directory.join("resources"))
+        .find(|directory| directory.is_dir())
+        .unwrap_or_else(|| PathBuf::from("resources"))
+}
+
+/// Path of a file inside the [resource directory](get_resource_directory).
+pub fn get_resource_path(relative_path: impl AsRef<Path>) -> PathBuf {
+    get_resource_directory().join(relative_path)
+}
+
+/// Name of the per-user directory inside the platform's config directory.
+pub const USER_CONFIG_DIRECTORY_NAME: &str = "strife";
+
+/// Per-user directory for settings players change, e.g. rebound input. `$XDG_CONFIG_HOME/strife`
+/// or `~/.config/strife` on Linux, `%APPDATA%\strife` on Windows and
+/// `~/Library/Application Support/strife` on macOS. `None` if the platform doesn't say where.
+pub fn get_user_config_directory() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    let base = if cfg!(target_os = "windows") {
+        non_empty("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        non_empty("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join(USER_CONFIG_DIRECTORY_NAME))
+}
@@ -0,0 +1,213 @@
+use std::{fmt::Display, path::PathBuf};
+
+use wgpu::rwh::HandleError;
+use winit::error::{EventLoopError, OsError};
+
+pub type SfResult<T> = Result<T, SfError>;
+
+#[derive(Debug)]
+pub enum SfError {
+    AdapterNotFound(wgpu::RequestAdapterError),
+    DeviceRequestFailed(wgpu::RequestDeviceError),
+    SurfaceCreationFailed(wgpu::CreateSurfaceError),
+    SurfaceLost,
+    SurfaceOutdated,
+    SurfaceTimeout,
+    SurfaceOutOfMemory,
+    SurfaceAcquireFailed,
+    NoSurfaceFormat,
+    DevicePollFailed(wgpu::PollError),
+    BufferMapFailed(wgpu::BufferAsyncError),
+    UnreadableTextureFormat(wgpu::TextureFormat),
+    WindowCreationFailed(OsError),
+    WindowHandleUnavailable(HandleError),
+    EventLoopFailed(EventLoopError),
+    EventLoopClosed,
+    UnsupportedAssetFormat(PathBuf),
+    ObjLoadFailed(PathBuf, tobj::LoadError),
+    GltfLoadFailed(PathBuf, gltf::Error),
+    EmptyMesh(PathBuf),
+    /// Path, submesh, the index and the submesh's vertex count.
+    MeshIndexOutOfBounds(PathBuf, String, u32, usize),
+    ImageLoadFailed(PathBuf, image::ImageError),
+    ImageDecodeFailed(image::ImageError),
+    /// Width, height and the device's maximum texture dimension.
+    TextureTooLarge(u32, u32, u32),
+    ShaderReadFailed(PathBuf, std::io::Error),
+    ShaderInvalid(String, String),
+    InputMapReadFailed(PathBuf, std::io::Error),
+    InputMapParseFailed(PathBuf, ron::error::SpannedError),
+    InputMapSerializeFailed(ron::Error),
+    InputMapWriteFailed(PathBuf, std::io::Error),
+    UserConfigDirectoryUnavailable,
+    RecordingWriteFailed(PathBuf, std::io::Error),
+    RecordingReadFailed(PathBuf, std::io::Error),
+    RecordingParseFailed(PathBuf, usize, ron::error::SpannedError),
+    RecordingSerializeFailed(ron::Error),
+    RecordingVersionUnsupported(PathBuf, u32),
+}
+
+impl Display for SfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SfError::AdapterNotFound(e) => write!(f, "no suitable graphics adapter found: {}", e),
+            SfError::DeviceRequestFailed(e) => write!(f, "graphics device request failed: {}", e),
+            SfError::SurfaceCreationFailed(e) => write!(f, "surface creation failed: {}", e),
+            SfError::SurfaceLost => write!(f, "surface lost"),
+            SfError::SurfaceOutdated => write!(f, "surface outdated"),
+            SfError::SurfaceTimeout => write!(f, "timed out acquiring surface texture"),
+            SfError::SurfaceOutOfMemory => write!(f, "out of memory acquiring surface texture"),
+            SfError::SurfaceAcquireFailed => write!(f, "failed to acquire surface texture"),
+            SfError::NoSurfaceFormat => write!(f, "surface reports no supported formats"),
+            SfError::DevicePollFailed(e) => {
+                write!(f, "waiting for the graphics device failed: {}", e)
+            }
+            SfError::BufferMapFailed(e) => write!(f, "buffer mapping failed: {}", e),
+            SfError::UnreadableTextureFormat(format) => {
+                write!(f, "texture format {:?} can't be read back", format)
+            }
+            SfError::WindowCreationFailed(e) => write!(f, "window creation failed: {}", e),
+            SfError::WindowHandleUnavailable(e) => write!(f, "window handle unavailable: {}", e),
+            SfError::EventLoopFailed(e) => write!(f, "event loop failed: {}", e),
+            SfError::EventLoopClosed => write!(f, "event loop is closed"),
+            SfError::UnsupportedAssetFormat(path) => {
+                write!(f, "unsupported asset format: {}", path.display())
+            }
+            SfError::ObjLoadFailed(path, e) => {
+                write!(f, "failed to load OBJ {}: {}", path.display(), e)
+            }
+            SfError::GltfLoadFailed(path, e) => {
+                write!(f, "failed to load glTF {}: {}", path.display(), e)
+            }
+            SfError::EmptyMesh(path) => {
+                write!(f, "{} contains no triangle geometry", path.display())
+            }
+            SfError::MeshIndexOutOfBounds(path, submesh, index, vertex_count) => {
+                write!(
+                    f,
+                    "{} submesh {} has index {} but only {} vertices",
+                    path.display(),
+                    submesh,
+                    index,
+                    vertex_count
+                )
+            }
+            SfError::ImageLoadFailed(path, e) => {
+                write!(f, "failed to load image {}: {}", path.display(), e)
+            }
+            SfError::ImageDecodeFailed(e) => write!(f, "failed to decode image: {}", e),
+            SfError::TextureTooLarge(width, height, max_dimension) => write!(
+                f,
+                "{}x{} image is larger than the maximum texture size {}",
+                width, height, max_dimension
+            ),
+            SfError::ShaderReadFailed(path, e) => {
+                write!(f, "failed to read shader {}: {}", path.display(), e)
+            }
+            SfError::ShaderInvalid(name, e) => write!(f, "invalid shader {}:\n{}", name, e),
+            SfError::InputMapReadFailed(path, e) => {
+                write!(f, "failed to read input map {}: {}", path.display(), e)
+            }
+            SfError::InputMapParseFailed(path, e) => {
+                write!(f, "failed to parse input map {}: {}", path.display(), e)
+            }
+            SfError::InputMapSerializeFailed(e) => {
+                write!(f, "failed to serialize input map: {}", e)
+            }
+            SfError::InputMapWriteFailed(path, e) => {
+                write!(f, "failed to write input map {}: {}", path.display(), e)
+            }
+            SfError::UserConfigDirectoryUnavailable => {
+                write!(f, "no config directory for the current user")
+            }
+            SfError::RecordingWriteFailed(path, e) => {
+                write!(f, "failed to write recording {}: {}", path.display(), e)
+            }
+            SfError::RecordingReadFailed(path, e) => {
+                write!(f, "failed to read recording {}: {}", path.display(), e)
+            }
+            SfError::RecordingParseFailed(path, line, e) => {
+                write!(
+                    f,
+                    "failed to parse recording {} line {}: {}",
+                    path.display(),
+                    line,
+                    e
+                )
+            }
+            SfError::RecordingSerializeFailed(e) => {
+                write!(f, "failed to serialize recorded event: {}", e)
+            }
+            SfError::RecordingVersionUnsupported(path, version) => {
+                write!(
+                    f,
+                    "recording {} has unsupported version {}",
+                    path.display(),
+                    version
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SfError {}
+
+impl From<wgpu::RequestAdapterError> for SfError {
+    fn from(value: wgpu::RequestAdapterError) -> Self {
+        SfError::AdapterNotFound(value)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for SfError {
+    fn from(value: wgpu::RequestDeviceError) -> Self {
+        SfError::DeviceRequestFailed(value)
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for SfError {
+    fn from(value: wgpu::CreateSurfaceError) -> Self {
+        SfError::SurfaceCreationFailed(value)
+    }
+}
+
+impl From<wgpu::SurfaceError> for SfError {
+    fn from(value: wgpu::SurfaceError) -> Self {
+        match value {
+            wgpu::SurfaceError::Lost => SfError::SurfaceLost,
+            wgpu::SurfaceError::Outdated => SfError::SurfaceOutdated,
+            wgpu::SurfaceError::Timeout => SfError::SurfaceTimeout,
+            wgpu::SurfaceError::OutOfMemory => SfError::SurfaceOutOfMemory,
+            wgpu::SurfaceError::Other => SfError::SurfaceAcquireFailed,
+        }
+    }
+}
+
+impl From<wgpu::PollError> for SfError {
+    fn from(value: wgpu::PollError) -> Self {
+        SfError::DevicePollFailed(value)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for SfError {
+    fn from(value: wgpu::BufferAsyncError) -> Self {
+        SfError::BufferMapFailed(value)
+    }
+}
+
+impl From<OsError> for SfError {
+    fn from(value: OsError) -> Self {
+        SfError::WindowCreationFailed(value)
+    }
+}
+
+impl From<HandleError> for SfError {
+    fn from(value: HandleError) -> Self {
+        SfError::WindowHandleUnavailable(value)
+    }
+}
+
+impl From<EventLoopError> for SfError {
+    fn from(value: EventLoopError) -> Self {
+        SfError::EventLoopFailed(value)
+    }
+}
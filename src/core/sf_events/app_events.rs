@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+use super::{EventCategory, EventCategoryFlags, Eventable};
+
+/// Sent when the application is resumed, after the surfaces of every window were created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppResumedEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for AppResumedEvent {
+    crate::serializable_event!("AppResumedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+/// Sent when the application is suspended, before the surfaces are dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSuspendedEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for AppSuspendedEvent {
+    crate::serializable_event!("AppSuspendedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+/// Sent once the event loop has handled all pending events and is about to wait for new ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppAboutToWaitEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for AppAboutToWaitEvent {
+    crate::serializable_event!("AppAboutToWaitEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
@@ -1,12 +1,18 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    cell::Cell,
     fmt::Debug,
+    ops::BitOr,
+    rc::Rc,
 };
 
-use crate::{info_core, warn_core};
+use serde::Serialize;
 
-#[derive(Debug)]
+use crate::info_core;
+
+use super::EventFamily;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EventCategory {
     NoCategory = 0,
     ApplicationCategory = 1,
@@ -15,18 +21,144 @@ pub enum EventCategory {
     MouseCategory = 1 << 3,
     MouseButtonCategory = 1 << 4,
     UserCategory = 1 << 5,
+    GamepadCategory = 1 << 6,
+}
+
+impl EventCategory {
+    /// Every category except [`EventCategory::NoCategory`].
+    pub const CATEGORIES: [EventCategory; 7] = [
+        EventCategory::ApplicationCategory,
+        EventCategory::InputCategory,
+        EventCategory::KeyboardCategory,
+        EventCategory::MouseCategory,
+        EventCategory::MouseButtonCategory,
+        EventCategory::UserCategory,
+        EventCategory::GamepadCategory,
+    ];
+}
+
+/// A set of [`EventCategory`]s, e.g. `EventCategory::InputCategory | EventCategory::MouseCategory`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EventCategoryFlags(u32);
+
+impl EventCategoryFlags {
+    pub const NONE: EventCategoryFlags = EventCategoryFlags(0);
+    pub const ALL: EventCategoryFlags = EventCategoryFlags(u32::MAX);
+
+    pub fn get_bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(&self, category: EventCategory) -> bool {
+        let bits = category as u32;
+        bits != 0 && self.0 & bits == bits
+    }
+
+    /// Whether the two sets share at least one category.
+    pub fn intersects(&self, other: EventCategoryFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn set(&mut self, category: EventCategory, is_included: bool) {
+        if is_included {
+            self.0 |= category as u32;
+        } else {
+            self.0 &= !(category as u32);
+        }
+    }
+}
+
+impl From<EventCategory> for EventCategoryFlags {
+    fn from(value: EventCategory) -> Self {
+        EventCategoryFlags(value as u32)
+    }
+}
+
+impl BitOr for EventCategory {
+    type Output = EventCategoryFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        EventCategoryFlags(self as u32 | rhs as u32)
+    }
 }
+
+impl BitOr<EventCategory> for EventCategoryFlags {
+    type Output = EventCategoryFlags;
+
+    fn bitor(self, rhs: EventCategory) -> Self::Output {
+        EventCategoryFlags(self.0 | rhs as u32)
+    }
+}
+
+impl BitOr for EventCategoryFlags {
+    type Output = EventCategoryFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        EventCategoryFlags(self.0 | rhs.0)
+    }
+}
+
 pub trait Eventable: Any + 'static {
     fn get_name(&self) -> &str;
 
+    /// Engine events report what they belong to, events defined by games default to
+    /// [`EventCategory::UserCategory`].
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::UserCategory.into()
+    }
+    fn is_in_category(&self, category: EventCategory) -> bool {
+        self.get_category_flags().contains(category)
+    }
+
     fn to_string(&self) -> String {
         String::from(self.get_name())
     }
+    /// Names the event type in recordings and the event inspector. Engine events return their
+    /// type name without the module path. The default is [`std::any::type_name`], which may change
+    /// between compiler versions, so events of games should return a fixed name.
+    fn get_type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+    /// The event's fields as RON, `None` for events that aren't serializable.
+    fn to_ron(&self) -> Option<String> {
+        None
+    }
     fn get_type_id(&self) -> TypeId {
         self.type_id()
     }
 
     fn is_handled(&self) -> bool;
+    fn set_handled(&mut self, is_handled: bool);
+}
+
+/// Implements [`Eventable::get_type_name`] and [`Eventable::to_ron`] for an event deriving
+/// `Serialize`, inside its `impl Eventable` block.
+///
+/// ```ignore
+/// impl Eventable for ExplosionEvent {
+///     serializable_event!("ExplosionEvent");
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! serializable_event {
+    ($type_name:literal) => {
+        fn get_type_name(&self) -> &'static str {
+            $type_name
+        }
+        fn to_ron(&self) -> Option<String> {
+            $crate::core::sf_events::event_to_ron(self)
+        }
+    };
+}
+
+/// Used by [`serializable_event!`](crate::serializable_event).
+pub fn event_to_ron<T: Serialize>(event: &T) -> Option<String> {
+    ron::to_string(event).ok()
 }
 
 pub struct Event<'a, T> {
@@ -43,26 +175,35 @@ pub trait AnyListener {
     fn handle_erased(&mut self, payload: &dyn Eventable) -> bool;
 }
 
-impl<'a, L> AnyListener for L
+impl<L> AnyListener for L
 where
-    L: EventListener + 'a,
+    L: EventListener,
 {
     fn handle_erased(&mut self, payload: &dyn Eventable) -> bool {
         if let Some(concrete_payload) =
             (payload as &dyn Any).downcast_ref::<L::EventableConcreteType>()
         {
-            let handled = self.handle(concrete_payload);
-            handled
+            self.handle(concrete_payload)
         } else {
             false
         }
     }
 }
 
-pub struct EventListenerStruct<E> {
-    callback: Box<dyn Fn(&E) -> bool + Send + 'static>,
+/// Calls a closure for every event of type `E`, see [`EventDispatcher::on`].
+pub struct EventListenerStruct<'a, E> {
+    callback: Box<dyn FnMut(&E) -> bool + 'a>,
+}
+
+impl<'a, E> EventListenerStruct<'a, E> {
+    pub fn new(callback: impl FnMut(&E) -> bool + 'a) -> Self {
+        Self {
+            callback: Box::new(callback),
+        }
+    }
 }
-impl<E> EventListener for EventListenerStruct<E>
+
+impl<E> EventListener for EventListenerStruct<'_, E>
 where
     E: Eventable,
 {
@@ -73,57 +214,515 @@ where
     }
 }
 
+/// Receives every event of the categories it was added for, whatever its concrete type.
+pub trait CategoryListener {
+    fn handle(&mut self, event: &dyn Eventable) -> bool;
+}
+
+impl<F> CategoryListener for F
+where
+    F: FnMut(&dyn Eventable) -> bool,
+{
+    fn handle(&mut self, event: &dyn Eventable) -> bool {
+        self(event)
+    }
+}
+
+struct CategoryListenerWrapper<L>(L);
+
+impl<L> AnyListener for CategoryListenerWrapper<L>
+where
+    L: CategoryListener,
+{
+    fn handle_erased(&mut self, payload: &dyn Eventable) -> bool {
+        self.0.handle(payload)
+    }
+}
+
+struct FamilyListener<'a, F: ?Sized> {
+    callback: Box<dyn FnMut(&F) -> bool + 'a>,
+}
+
+impl<F> AnyListener for FamilyListener<'_, F>
+where
+    F: EventFamily + ?Sized,
+{
+    fn handle_erased(&mut self, payload: &dyn Eventable) -> bool {
+        F::from_event(payload).is_some_and(|event| (self.callback)(event))
+    }
+}
+
+fn is_family_member<F: EventFamily + ?Sized>(event: &dyn Eventable) -> bool {
+    F::from_event(event).is_some()
+}
+
+/// Identifies a listener within its [`EventDispatcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+/// How a listener is added to an [`EventDispatcher`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListenerOptions {
+    /// Listeners with a higher priority get events first, equal priorities keep the order they were added in.
+    pub priority: i32,
+    /// Removes the listener after its first event.
+    pub is_one_shot: bool,
+    /// Shown by the event inspector when the listener handles an event.
+    pub name: Option<&'static str>,
+}
+
+impl ListenerOptions {
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn one_shot(mut self) -> Self {
+        self.is_one_shot = true;
+        self
+    }
+
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+}
+
+/// Removes its listener from the [`EventDispatcher`] when dropped.
+#[must_use = "dropping a Subscription removes its listener, use `forget` to keep it"]
+pub struct Subscription {
+    id: ListenerId,
+    is_active: Option<Rc<Cell<bool>>>,
+}
+
+impl Subscription {
+    pub fn get_id(&self) -> ListenerId {
+        self.id
+    }
+
+    /// `false` once the listener was removed, one-shot listeners are removed after their event.
+    pub fn is_active(&self) -> bool {
+        self.is_active
+            .as_ref()
+            .is_some_and(|is_active| is_active.get())
+    }
+
+    pub fn unsubscribe(self) {}
+
+    /// Keeps the listener for as long as the dispatcher lives, it can still be removed by id.
+    pub fn forget(mut self) -> ListenerId {
+        self.is_active = None;
+        self.id
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(is_active) = &self.is_active {
+            is_active.set(false);
+        }
+    }
+}
+
+enum ListenerFilter {
+    Type(TypeId),
+    Categories(EventCategoryFlags),
+    Family(fn(&dyn Eventable) -> bool),
+}
+
+struct ListenerEntry<'a> {
+    id: ListenerId,
+    filter: ListenerFilter,
+    options: ListenerOptions,
+    /// Shared with the [`Subscription`], inactive entries are dropped on the next dispatch.
+    is_active: Rc<Cell<bool>>,
+    listener: Box<dyn AnyListener + 'a>,
+}
+
+impl ListenerEntry<'_> {
+    fn accepts(&self, event: &dyn Eventable) -> bool {
+        self.is_active.get()
+            && match self.filter {
+                ListenerFilter::Type(type_id) => type_id == event.get_type_id(),
+                ListenerFilter::Categories(categories) => {
+                    categories.intersects(event.get_category_flags())
+                }
+                ListenerFilter::Family(is_member) => is_member(event),
+            }
+    }
+}
+
 pub struct EventDispatcher<'a> {
-    event_listeners: HashMap<TypeId, Vec<Box<dyn AnyListener + 'a>>>,
+    listeners: Vec<ListenerEntry<'a>>,
+    next_listener_id: u64,
+}
+
+impl Default for EventDispatcher<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> EventDispatcher<'a> {
     pub fn new() -> EventDispatcher<'a> {
         Self {
-            event_listeners: HashMap::new(),
+            listeners: Vec::new(),
+            next_listener_id: 0,
         }
     }
-    pub fn add_listener<L>(&mut self, listener: L)
+
+    pub fn add_listener<L>(&mut self, listener: L) -> Subscription
     where
         L: EventListener + 'a,
     {
-        let type_id = std::any::TypeId::of::<L::EventableConcreteType>();
-        self.event_listeners
-            .entry(type_id)
-            .or_insert_with(Vec::new)
-            .push(Box::new(listener))
+        self.add_listener_with(ListenerOptions::default(), listener)
     }
 
-    pub fn log_listeners(&self) {
-        self.event_listeners
+    pub fn add_listener_with<L>(&mut self, options: ListenerOptions, listener: L) -> Subscription
+    where
+        L: EventListener + 'a,
+    {
+        let type_id = TypeId::of::<L::EventableConcreteType>();
+        self.insert(ListenerFilter::Type(type_id), options, Box::new(listener))
+    }
+
+    /// Adds a listener for events in any of `categories`.
+    pub fn add_category_listener<L>(
+        &mut self,
+        categories: impl Into<EventCategoryFlags>,
+        listener: L,
+    ) -> Subscription
+    where
+        L: CategoryListener + 'a,
+    {
+        self.add_category_listener_with(categories, ListenerOptions::default(), listener)
+    }
+
+    pub fn add_category_listener_with<L>(
+        &mut self,
+        categories: impl Into<EventCategoryFlags>,
+        options: ListenerOptions,
+        listener: L,
+    ) -> Subscription
+    where
+        L: CategoryListener + 'a,
+    {
+        self.insert(
+            ListenerFilter::Categories(categories.into()),
+            options,
+            Box::new(CategoryListenerWrapper(listener)),
+        )
+    }
+
+    /// Calls `callback` for every event of type `E`, returning `true` marks the event as handled.
+    /// The closure may borrow state that outlives the dispatcher.
+    pub fn on<E>(&mut self, callback: impl FnMut(&E) -> bool + 'a) -> Subscription
+    where
+        E: Eventable,
+    {
+        self.on_with(ListenerOptions::default(), callback)
+    }
+
+    pub fn on_with<E>(
+        &mut self,
+        options: ListenerOptions,
+        callback: impl FnMut(&E) -> bool + 'a,
+    ) -> Subscription
+    where
+        E: Eventable,
+    {
+        self.add_listener_with(options, EventListenerStruct::new(callback))
+    }
+
+    /// Calls `callback` for every event of the family `F`, e.g. `dyn WindowScopedEvent`.
+    pub fn on_family<F>(&mut self, callback: impl FnMut(&F) -> bool + 'a) -> Subscription
+    where
+        F: EventFamily + ?Sized,
+    {
+        self.on_family_with(ListenerOptions::default(), callback)
+    }
+
+    pub fn on_family_with<F>(
+        &mut self,
+        options: ListenerOptions,
+        callback: impl FnMut(&F) -> bool + 'a,
+    ) -> Subscription
+    where
+        F: EventFamily + ?Sized,
+    {
+        let listener = FamilyListener {
+            callback: Box::new(callback),
+        };
+        self.insert(
+            ListenerFilter::Family(is_family_member::<F>),
+            options,
+            Box::new(listener),
+        )
+    }
+
+    /// Returns whether the listener was still there.
+    pub fn remove_listener(&mut self, id: ListenerId) -> bool {
+        let Some(index) = self
+            .listeners
+            .iter()
+            .position(|entry| entry.id == id && entry.is_active.get())
+        else {
+            return false;
+        };
+        let entry = self.listeners.remove(index);
+        entry.is_active.set(false);
+        true
+    }
+
+    pub fn get_listener_count(&self) -> usize {
+        self.listeners
             .iter()
-            .for_each(|(key, value)| info_core!("{}", format!("{:?}: {:?}", key, value.len())));
+            .filter(|entry| entry.is_active.get())
+            .count()
     }
 
-    pub fn dispatch_dynamic(&mut self, event: &dyn Eventable) {
-        let type_id = event.get_type_id();
-        if let Some(listeners) = self.event_listeners.get_mut(&type_id) {
-            for listener in listeners.iter_mut() {
-                let handled = listener.handle_erased(event);
-                if handled {
-                    break;
+    pub fn log_listeners(&self) {
+        for entry in self.listeners.iter().filter(|entry| entry.is_active.get()) {
+            match entry.filter {
+                ListenerFilter::Type(type_id) => {
+                    info_core!(
+                        "{:?}: {:?}, priority {}",
+                        entry.id,
+                        type_id,
+                        entry.options.priority
+                    )
                 }
+                ListenerFilter::Categories(categories) => info_core!(
+                    "{:?}: categories {:#b}, priority {}",
+                    entry.id,
+                    categories.get_bits(),
+                    entry.options.priority
+                ),
+                ListenerFilter::Family(_) => {
+                    info_core!(
+                        "{:?}: family, priority {}",
+                        entry.id,
+                        entry.options.priority
+                    )
+                }
+            }
+        }
+    }
+
+    /// Delivers the event to the listeners of its concrete type or categories, by priority,
+    /// until one of them returns `true`, which marks the event as handled. Already handled events
+    /// are not delivered.
+    /// Returns whether the event is handled afterwards.
+    pub fn dispatch_dynamic(&mut self, event: &mut dyn Eventable) -> bool {
+        self.dispatch_and_get_handler(event);
+        event.is_handled()
+    }
+
+    /// Like [`Self::dispatch_dynamic`], but returns the listener that handled the event.
+    /// `None` if no listener did, also when the event was handled before.
+    pub fn dispatch_and_get_handler(&mut self, event: &mut dyn Eventable) -> Option<ListenerId> {
+        self.listeners.retain(|entry| entry.is_active.get());
+        if event.is_handled() {
+            return None;
+        }
+
+        for entry in self.listeners.iter_mut() {
+            if !entry.accepts(event) {
+                continue;
+            }
+            if entry.options.is_one_shot {
+                entry.is_active.set(false);
+            }
+            if entry.listener.handle_erased(event) {
+                event.set_handled(true);
+                return Some(entry.id);
             }
         }
+        None
     }
 
-    pub fn dispatch<T>(&mut self, event: &T)
+    /// The name the listener was added with, also right after a one-shot listener was called.
+    pub fn get_listener_name(&self, id: ListenerId) -> Option<&'static str> {
+        self.listeners
+            .iter()
+            .find(|entry| entry.id == id)
+            .and_then(|entry| entry.options.name)
+    }
+
+    pub fn dispatch<T>(&mut self, event: &mut T) -> bool
     where
         T: Eventable,
     {
-        let type_id = std::any::TypeId::of::<T>();
-        if let Some(listeners) = self.event_listeners.get_mut(&type_id) {
-            for listener in listeners.iter_mut() {
-                let handled = listener.handle_erased(event);
-                if handled {
-                    break;
-                }
-            }
+        self.dispatch_dynamic(event)
+    }
+
+    fn insert(
+        &mut self,
+        filter: ListenerFilter,
+        options: ListenerOptions,
+        listener: Box<dyn AnyListener + 'a>,
+    ) -> Subscription {
+        let id = ListenerId(self.next_listener_id);
+        self.next_listener_id += 1;
+        let is_active = Rc::new(Cell::new(true));
+
+        let index = self
+            .listeners
+            .iter()
+            .position(|entry| entry.options.priority < options.priority)
+            .unwrap_or(self.listeners.len());
+        self.listeners.insert(
+            index,
+            ListenerEntry {
+                id,
+                filter,
+                options,
+                is_active: is_active.clone(),
+                listener,
+            },
+        );
+
+        Subscription {
+            id,
+            is_active: Some(is_active),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_utils::{Log, LoggingListener, TestEvent};
+
+    fn dispatch(dispatcher: &mut EventDispatcher) -> bool {
+        dispatcher.dispatch(&mut TestEvent::new("test"))
+    }
+
+    #[test]
+    fn listeners_run_by_priority_then_insertion_order() {
+        let log = Log::default();
+        let mut dispatcher = EventDispatcher::new();
+        let _subscriptions = [
+            dispatcher.add_listener(LoggingListener::new("first", &log)),
+            dispatcher.add_listener_with(
+                ListenerOptions::default().with_priority(-1),
+                LoggingListener::new("last", &log),
+            ),
+            dispatcher.add_category_listener_with(
+                EventCategory::UserCategory,
+                ListenerOptions::default().with_priority(10),
+                LoggingListener::new("high", &log),
+            ),
+            dispatcher.add_listener(LoggingListener::new("second", &log)),
+        ];
+
+        dispatch(&mut dispatcher);
+        assert_eq!(*log.borrow(), ["high", "first", "second", "last"]);
+    }
+
+    #[test]
+    fn handling_stops_lower_priority_listeners() {
+        let log = Log::default();
+        let mut dispatcher = EventDispatcher::new();
+        let _handler = dispatcher.add_listener(LoggingListener {
+            handles: true,
+            ..LoggingListener::new("handler", &log)
+        });
+        let _ignored = dispatcher.add_listener(LoggingListener::new("ignored", &log));
+
+        assert!(dispatch(&mut dispatcher));
+        assert_eq!(*log.borrow(), ["handler"]);
+    }
+
+    #[test]
+    fn dropping_or_removing_stops_delivery() {
+        let log = Log::default();
+        let mut dispatcher = EventDispatcher::new();
+        let dropped = dispatcher.add_listener(LoggingListener::new("dropped", &log));
+        let removed = dispatcher
+            .add_listener(LoggingListener::new("removed", &log))
+            .forget();
+        let kept = dispatcher.add_listener(LoggingListener::new("kept", &log));
+
+        drop(dropped);
+        assert!(dispatcher.remove_listener(removed));
+        assert!(!dispatcher.remove_listener(removed));
+        dispatch(&mut dispatcher);
+
+        assert_eq!(*log.borrow(), ["kept"]);
+        assert_eq!(dispatcher.get_listener_count(), 1);
+        assert!(kept.is_active());
+    }
+
+    #[test]
+    fn one_shot_listeners_run_once() {
+        let log = Log::default();
+        let mut dispatcher = EventDispatcher::new();
+        let once = dispatcher.add_listener_with(
+            ListenerOptions::default().one_shot(),
+            LoggingListener::new("once", &log),
+        );
+
+        dispatch(&mut dispatcher);
+        dispatch(&mut dispatcher);
+        assert_eq!(*log.borrow(), ["once"]);
+        assert!(!once.is_active());
+    }
+
+    #[test]
+    fn closures_can_borrow_local_state() {
+        let mut received = Vec::new();
+        {
+            let mut dispatcher = EventDispatcher::new();
+            let _subscription = dispatcher.on::<TestEvent>(|event| {
+                received.push(event.get_name().to_string());
+                false
+            });
+            dispatch(&mut dispatcher);
+        }
+        assert_eq!(received, ["test"]);
+    }
+
+    trait Scored: Eventable {
+        fn get_score(&self) -> u32;
+    }
+
+    impl Scored for TestEvent {
+        fn get_score(&self) -> u32 {
+            3
+        }
+    }
+
+    struct OtherEvent;
+
+    impl Eventable for OtherEvent {
+        fn get_name(&self) -> &str {
+            "other"
+        }
+        fn is_handled(&self) -> bool {
+            false
+        }
+        fn set_handled(&mut self, _is_handled: bool) {}
+    }
+
+    crate::event_family!(dyn Scored: TestEvent);
+
+    #[test]
+    fn family_listeners_get_every_member() {
+        let score = Cell::new(0);
+        let mut dispatcher = EventDispatcher::new();
+        let once = dispatcher.on_family_with::<dyn Scored>(
+            ListenerOptions::default().one_shot(),
+            |event| {
+                score.set(score.get() + event.get_score());
+                true
+            },
+        );
+
+        assert!(!dispatcher.dispatch(&mut OtherEvent));
+        assert!(once.is_active());
+        assert!(dispatch(&mut dispatcher));
+        assert_eq!(score.get(), 3);
+        assert!(!once.is_active());
+    }
+}
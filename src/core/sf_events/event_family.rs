@@ -0,0 +1,96 @@
+use winit::window::WindowId;
+
+use super::{
+    CursorEnteredEvent, CursorLeftEvent, Eventable, FileDroppedEvent, ImeEvent, KeyPressedEvent,
+    KeyReleasedEvent, ModifiersChangedEvent, MouseButtonPressedEvent, MouseButtonReleasedEvent,
+    MouseMoveEvent, MouseScrolledEvent, TextInputEvent, TouchEvent, WindowCloseEvent,
+    WindowFocusEvent, WindowMovedEvent, WindowOccludedEvent, WindowOpenedEvent,
+    WindowRedrawRequestedEvent, WindowResizeEvent, WindowScaleFactorChangedEvent,
+};
+
+/// A group of event types seen through a shared trait object, e.g. `dyn WindowScopedEvent`,
+/// so one listener can handle all of them, see [`EventDispatcher::on_family`](super::EventDispatcher::on_family).
+/// Usually implemented with [`event_family!`](crate::event_family).
+pub trait EventFamily: 'static {
+    /// `None` for events outside of the family.
+    fn from_event(event: &dyn Eventable) -> Option<&Self>;
+}
+
+/// Implements [`EventFamily`] for a trait object by listing its member event types,
+/// which all have to implement the trait.
+///
+/// ```ignore
+/// event_family!(dyn Damage: ExplosionEvent, FallEvent);
+/// ```
+#[macro_export]
+macro_rules! event_family {
+    ($family:ty: $($event:ty),+ $(,)?) => {
+        impl $crate::core::sf_events::EventFamily for $family {
+            fn from_event(event: &dyn $crate::core::sf_events::Eventable) -> Option<&Self> {
+                let event = event as &dyn ::std::any::Any;
+                $(
+                    if let Some(event) = event.downcast_ref::<$event>() {
+                        return Some(event);
+                    }
+                )+
+                None
+            }
+        }
+    };
+}
+
+/// Events that happened in one window.
+pub trait WindowScopedEvent: Eventable {
+    fn get_window_id(&self) -> WindowId;
+}
+
+/// Registers the members of [`WindowScopedEvent`] in one list: the events reading their `window_id`
+/// field get the impl generated, the others implement it by hand.
+macro_rules! window_scoped_events {
+    (
+        with_window_id_field: [$($event:ty),+ $(,)?],
+        implemented_by_hand: [$($other_event:ty),* $(,)?] $(,)?
+    ) => {
+        $(
+            impl WindowScopedEvent for $event {
+                fn get_window_id(&self) -> WindowId {
+                    self.window_id
+                }
+            }
+        )+
+
+        event_family!(dyn WindowScopedEvent: $($event,)+ $($other_event,)*);
+    };
+}
+
+window_scoped_events!(
+    with_window_id_field: [
+        KeyPressedEvent,
+        KeyReleasedEvent,
+        TextInputEvent,
+        ImeEvent,
+        ModifiersChangedEvent,
+        MouseMoveEvent,
+        MouseButtonPressedEvent,
+        MouseButtonReleasedEvent,
+        MouseScrolledEvent,
+        CursorEnteredEvent,
+        CursorLeftEvent,
+        TouchEvent,
+        WindowCloseEvent,
+        WindowResizeEvent,
+        WindowRedrawRequestedEvent,
+        WindowFocusEvent,
+        WindowMovedEvent,
+        WindowScaleFactorChangedEvent,
+        WindowOccludedEvent,
+        FileDroppedEvent,
+    ],
+    implemented_by_hand: [WindowOpenedEvent],
+);
+
+impl WindowScopedEvent for WindowOpenedEvent {
+    fn get_window_id(&self) -> WindowId {
+        self.window.id()
+    }
+}
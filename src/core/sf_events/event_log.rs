@@ -0,0 +1,147 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use super::{EventCategoryFlags, Eventable, ListenerId};
+
+/// What marked a logged event as handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventHandler {
+    /// A listener outside the layers, with the name from its
+    /// [`ListenerOptions`](super::ListenerOptions) if it has one.
+    Listener {
+        id: ListenerId,
+        name: Option<&'static str>,
+    },
+    /// The layer with this name.
+    Layer(String),
+}
+
+/// An event as it was delivered, see [`EventLog`].
+#[derive(Debug, Clone)]
+pub struct LoggedEvent {
+    pub type_name: &'static str,
+    pub categories: EventCategoryFlags,
+    pub description: String,
+    /// The event's fields as RON, `None` for events that aren't serializable.
+    pub data: Option<String>,
+    pub handled_by: Option<EventHandler>,
+}
+
+impl LoggedEvent {
+    pub fn new(event: &dyn Eventable, handled_by: Option<EventHandler>) -> Self {
+        Self {
+            type_name: event.get_type_name(),
+            categories: event.get_category_flags(),
+            description: event.to_string(),
+            data: event.to_ron(),
+            handled_by,
+        }
+    }
+}
+
+/// The events delivered from the start of one frame to the start of the next.
+#[derive(Debug, Clone, Default)]
+pub struct LoggedFrame {
+    pub index: u64,
+    pub events: Vec<LoggedEvent>,
+}
+
+struct EventLogState {
+    frames: VecDeque<LoggedFrame>,
+    max_frames: usize,
+    next_frame_index: u64,
+    is_paused: bool,
+    is_enabled: bool,
+}
+
+/// Keeps the events of the last few frames for debugging, e.g. in the event inspector of `sf_gui`.
+/// Clones share the same log.
+#[derive(Clone)]
+pub struct EventLog {
+    state: Rc<RefCell<EventLogState>>,
+}
+
+impl EventLog {
+    pub fn new(max_frames: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(EventLogState {
+                frames: VecDeque::new(),
+                max_frames,
+                next_frame_index: 0,
+                is_paused: false,
+                is_enabled: true,
+            })),
+        }
+    }
+
+    /// Events logged from now on belong to a new frame, the oldest frames are dropped.
+    pub fn begin_frame(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.is_paused || !state.is_enabled {
+            return;
+        }
+        let index = state.next_frame_index;
+        state.next_frame_index += 1;
+        state.frames.push_back(LoggedFrame {
+            index,
+            events: Vec::new(),
+        });
+        while state.frames.len() > state.max_frames {
+            state.frames.pop_front();
+        }
+    }
+
+    pub fn log(&self, event: LoggedEvent) {
+        let mut state = self.state.borrow_mut();
+        if state.is_paused || !state.is_enabled {
+            return;
+        }
+        if state.frames.is_empty() {
+            state.frames.push_back(LoggedFrame::default());
+            state.next_frame_index = 1;
+        }
+        if let Some(frame) = state.frames.back_mut() {
+            frame.events.push(event);
+        }
+    }
+
+    /// Nothing is logged while paused, so the logged frames can be looked at.
+    pub fn is_paused(&self) -> bool {
+        self.state.borrow().is_paused
+    }
+    pub fn set_paused(&self, is_paused: bool) {
+        self.state.borrow_mut().is_paused = is_paused;
+    }
+
+    /// A disabled log keeps nothing, for when nobody looks at it, e.g. while the event inspector is
+    /// collapsed. Unlike pausing it's meant to be controlled by the viewer, not the user.
+    pub fn is_enabled(&self) -> bool {
+        self.state.borrow().is_enabled
+    }
+    pub fn set_enabled(&self, is_enabled: bool) {
+        self.state.borrow_mut().is_enabled = is_enabled;
+    }
+
+    /// Whether events given to [`EventLog::log`] are kept, so callers can skip building them.
+    pub fn is_logging(&self) -> bool {
+        let state = self.state.borrow();
+        state.is_enabled && !state.is_paused
+    }
+
+    pub fn clear(&self) {
+        self.state.borrow_mut().frames.clear();
+    }
+
+    /// Oldest first.
+    pub fn get_frames(&self) -> Vec<LoggedFrame> {
+        self.state.borrow().frames.iter().cloned().collect()
+    }
+
+    /// Reads the frames, oldest first, without copying them.
+    pub fn with_frames<R>(&self, f: impl FnOnce(&VecDeque<LoggedFrame>) -> R) -> R {
+        f(&self.state.borrow().frames)
+    }
+
+    pub fn get_max_frames(&self) -> usize {
+        self.state.borrow().max_frames
+    }
+}
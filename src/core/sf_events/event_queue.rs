@@ -0,0 +1,68 @@
+use std::{cell::RefCell, collections::VecDeque, mem, rc::Rc};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::{
+    core::sf_error::{SfError, SfResult},
+    sf_window::WindowManagerCustomEvent,
+};
+
+use super::Eventable;
+
+#[derive(Default)]
+struct EventQueueState {
+    sent: VecDeque<Box<dyn Eventable>>,
+    posted: Vec<Box<dyn Eventable>>,
+}
+
+/// Queues events from places that can't dispatch them, like a layer's `on_update` or `on_event`.
+/// The events go through the same listeners and layers as the ones coming from the window.
+#[derive(Clone, Default)]
+pub struct EventQueue {
+    queue: Rc<RefCell<EventQueueState>>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delivers `event` at the start of the next frame, before the layers update.
+    pub fn post<E: Eventable>(&self, event: E) {
+        self.queue.borrow_mut().posted.push(Box::new(event));
+    }
+
+    /// Delivers `event` as soon as the current event or frame is done, before the event loop continues.
+    pub fn send<E: Eventable>(&self, event: E) {
+        self.queue.borrow_mut().sent.push_back(Box::new(event));
+    }
+
+    pub fn take_sent(&self) -> Option<Box<dyn Eventable>> {
+        self.queue.borrow_mut().sent.pop_front()
+    }
+
+    /// Events posted while these are delivered wait for the frame after.
+    pub fn take_posted(&self) -> Vec<Box<dyn Eventable>> {
+        mem::take(&mut self.queue.borrow_mut().posted)
+    }
+}
+
+/// Sends events from any thread, e.g. a worker that finished loading.
+/// They are delivered on the main thread once the event loop picks them up.
+#[derive(Clone)]
+pub struct EventSender {
+    event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
+}
+
+impl EventSender {
+    pub fn new(event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>) -> Self {
+        Self { event_loop_proxy }
+    }
+
+    /// Fails once the event loop has exited.
+    pub fn send<E: Eventable + Send>(&self, event: E) -> SfResult<()> {
+        self.event_loop_proxy
+            .send_event(WindowManagerCustomEvent::SendEvent(Box::new(event)))
+            .map_err(|_| SfError::EventLoopClosed)
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::sf_input::{GamepadAxis, GamepadButton, GamepadId};
+
+use super::{EventCategory, EventCategoryFlags, Eventable};
+
+//Gamepad Connected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadConnectedEvent {
+    pub name: String,
+    pub gamepad_id: GamepadId,
+    pub gamepad_name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for GamepadConnectedEvent {
+    crate::serializable_event!("GamepadConnectedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::GamepadCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "Event {}, {:?}: {}",
+            &self.name, &self.gamepad_id, &self.gamepad_name
+        )
+    }
+}
+
+impl GamepadConnectedEvent {
+    pub fn get_gamepad_id(&self) -> GamepadId {
+        self.gamepad_id
+    }
+    pub fn get_gamepad_name(&self) -> &str {
+        &self.gamepad_name
+    }
+}
+
+//Gamepad Disconnected
+/// Buttons the gamepad held are released in [`crate::core::sf_input::Input`], no release events are sent for them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadDisconnectedEvent {
+    pub name: String,
+    pub gamepad_id: GamepadId,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for GamepadDisconnectedEvent {
+    crate::serializable_event!("GamepadDisconnectedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::GamepadCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!("Event {}, {:?}", &self.name, &self.gamepad_id)
+    }
+}
+
+impl GamepadDisconnectedEvent {
+    pub fn get_gamepad_id(&self) -> GamepadId {
+        self.gamepad_id
+    }
+}
+
+//Gamepad Button Pressed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadButtonPressedEvent {
+    pub name: String,
+    pub gamepad_id: GamepadId,
+    pub button: GamepadButton,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for GamepadButtonPressedEvent {
+    crate::serializable_event!("GamepadButtonPressedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::GamepadCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "Event {}, {:?}, button: {:?}",
+            &self.name, &self.gamepad_id, &self.button
+        )
+    }
+}
+
+impl GamepadButtonPressedEvent {
+    pub fn get_gamepad_id(&self) -> GamepadId {
+        self.gamepad_id
+    }
+    pub fn get_button(&self) -> GamepadButton {
+        self.button
+    }
+}
+
+//Gamepad Button Released
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadButtonReleasedEvent {
+    pub name: String,
+    pub gamepad_id: GamepadId,
+    pub button: GamepadButton,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for GamepadButtonReleasedEvent {
+    crate::serializable_event!("GamepadButtonReleasedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::GamepadCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "Event {}, {:?}, button: {:?}",
+            &self.name, &self.gamepad_id, &self.button
+        )
+    }
+}
+
+impl GamepadButtonReleasedEvent {
+    pub fn get_gamepad_id(&self) -> GamepadId {
+        self.gamepad_id
+    }
+    pub fn get_button(&self) -> GamepadButton {
+        self.button
+    }
+}
+
+//Gamepad Axis Moved
+/// Only sent when the value after the deadzone changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadAxisMovedEvent {
+    pub name: String,
+    pub gamepad_id: GamepadId,
+    pub axis: GamepadAxis,
+    pub value: f32,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for GamepadAxisMovedEvent {
+    crate::serializable_event!("GamepadAxisMovedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::GamepadCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "Event {}, {:?}, {:?}: {}",
+            &self.name, &self.gamepad_id, &self.axis, self.value
+        )
+    }
+}
+
+impl GamepadAxisMovedEvent {
+    pub fn get_gamepad_id(&self) -> GamepadId {
+        self.gamepad_id
+    }
+    pub fn get_axis(&self) -> GamepadAxis {
+        self.axis
+    }
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+}
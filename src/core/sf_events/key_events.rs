@@ -1,18 +1,29 @@
-use super::Eventable;
+use serde::{Deserialize, Serialize};
+use winit::{event::Ime, keyboard::ModifiersState, window::WindowId};
+
+use super::{EventCategory, EventCategoryFlags, Eventable};
 
 //Key Pressed
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyPressedEvent {
     pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
     pub keycode: winit::keyboard::KeyCode,
     pub repeat: bool,
+    #[serde(skip)]
     pub is_handled: bool,
 }
 
 impl Eventable for KeyPressedEvent {
+    crate::serializable_event!("KeyPressedEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::KeyboardCategory
+    }
 
     fn to_string(&self) -> String {
         format!("Event {}, keycode: {:?}", &self.name, &self.keycode)
@@ -21,9 +32,15 @@ impl Eventable for KeyPressedEvent {
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 
 impl KeyPressedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
     pub fn get_keycode(&self) -> winit::keyboard::KeyCode {
         self.keycode
     }
@@ -33,27 +50,176 @@ impl KeyPressedEvent {
 }
 
 //Key Released
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyReleasedEvent {
     pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
     pub keycode: winit::keyboard::KeyCode,
+    #[serde(skip)]
     pub is_handled: bool,
 }
 
 impl Eventable for KeyReleasedEvent {
+    crate::serializable_event!("KeyReleasedEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::KeyboardCategory
+    }
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
     fn to_string(&self) -> String {
         format!("Event {}, keycode: {:?}", &self.name, &self.keycode)
     }
 }
 
 impl KeyReleasedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
     pub fn get_keycode(&self) -> winit::keyboard::KeyCode {
         self.keycode
     }
 }
+
+//Text Input
+/// Text produced by a key press or committed by an input method, control characters are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextInputEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub text: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for TextInputEvent {
+    crate::serializable_event!("TextInputEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::KeyboardCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!("Event {}, text: {:?}", &self.name, &self.text)
+    }
+}
+
+impl TextInputEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+}
+
+//IME
+/// Input method state changes, including the preedit text while composing.
+/// Committed text is also sent as a [`TextInputEvent`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImeEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub ime: Ime,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for ImeEvent {
+    crate::serializable_event!("ImeEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::KeyboardCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!("Event {}, {:?}", &self.name, &self.ime)
+    }
+}
+
+impl ImeEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_ime(&self) -> &Ime {
+        &self.ime
+    }
+}
+
+//Modifiers Changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModifiersChangedEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub modifiers: ModifiersState,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for ModifiersChangedEvent {
+    crate::serializable_event!("ModifiersChangedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::KeyboardCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+    fn to_string(&self) -> String {
+        format!("Event {}, modifiers: {:?}", &self.name, &self.modifiers)
+    }
+}
+
+impl ModifiersChangedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+    pub fn is_shift_pressed(&self) -> bool {
+        self.modifiers.shift_key()
+    }
+    pub fn is_control_pressed(&self) -> bool {
+        self.modifiers.control_key()
+    }
+    pub fn is_alt_pressed(&self) -> bool {
+        self.modifiers.alt_key()
+    }
+    pub fn is_super_pressed(&self) -> bool {
+        self.modifiers.super_key()
+    }
+}
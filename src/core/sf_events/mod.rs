@@ -1,8 +1,19 @@
+pub mod app_events;
+pub use app_events::*;
 pub mod event;
 pub use event::*;
+pub mod event_family;
+pub use event_family::*;
+pub mod event_log;
+pub use event_log::*;
+pub mod event_queue;
+pub use event_queue::*;
+pub mod gamepad_events;
+pub use gamepad_events::*;
 pub mod key_events;
 pub use key_events::*;
 pub mod mouse_events;
 pub use mouse_events::*;
 pub mod window_events;
 pub use window_events::*;
+mod window_id_serde;
@@ -1,30 +1,49 @@
-use winit::event::{DeviceId, MouseButton};
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::{DeviceId, MouseButton, MouseScrollDelta, TouchPhase},
+    window::WindowId,
+};
 
-use super::Eventable;
+use super::{EventCategory, EventCategoryFlags, Eventable};
 
 //Key Pressed
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MouseMoveEvent {
     pub name: String,
     pub x: f64,
     pub y: f64,
+    #[serde(skip)]
     pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
     pub device_id: DeviceId,
 }
 
 impl Eventable for MouseMoveEvent {
+    crate::serializable_event!("MouseMoveEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::MouseCategory
+    }
     fn to_string(&self) -> String {
         format!("{}: x- {}, y- {}", self.name, self.x, self.y)
     }
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 
 impl MouseMoveEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
     pub fn get_device_id(&self) -> &DeviceId {
         &self.device_id
     }
@@ -45,18 +64,29 @@ fn mouse_button_to_string(button: &MouseButton) -> String {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MouseButtonPressedEvent {
     pub name: String,
+    #[serde(skip)]
     pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
     pub device_id: DeviceId,
     pub button: MouseButton,
 }
 
 impl Eventable for MouseButtonPressedEvent {
+    crate::serializable_event!("MouseButtonPressedEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory
+            | EventCategory::MouseCategory
+            | EventCategory::MouseButtonCategory
+    }
     fn to_string(&self) -> String {
         format!(
             "{}: button - {}, ",
@@ -67,29 +97,46 @@ impl Eventable for MouseButtonPressedEvent {
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 impl MouseButtonPressedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
     pub fn get_device_id(&self) -> &DeviceId {
         &self.device_id
     }
-    fn get_button(&self) -> &MouseButton {
+    pub fn get_button(&self) -> &MouseButton {
         &self.button
     }
 }
 //Mouse Released Event
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MouseButtonReleasedEvent {
     pub name: String,
+    #[serde(skip)]
     pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
     pub device_id: DeviceId,
     pub button: MouseButton,
 }
 
 impl Eventable for MouseButtonReleasedEvent {
+    crate::serializable_event!("MouseButtonReleasedEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory
+            | EventCategory::MouseCategory
+            | EventCategory::MouseButtonCategory
+    }
     fn to_string(&self) -> String {
         format!(
             "{}: button - {}, ",
@@ -100,8 +147,14 @@ impl Eventable for MouseButtonReleasedEvent {
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 impl MouseButtonReleasedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
     pub fn get_device_id(&self) -> &DeviceId {
         &self.device_id
     }
@@ -109,3 +162,230 @@ impl MouseButtonReleasedEvent {
         &self.button
     }
 }
+
+//Mouse Scrolled Event
+/// What the offsets of a [`MouseScrolledEvent`] are measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollUnit {
+    /// Wheel notches, usually from a mouse wheel.
+    Lines,
+    /// Physical pixels, usually from a touchpad.
+    Pixels,
+}
+
+pub fn scroll_delta_to_offset(delta: MouseScrollDelta) -> (f64, f64, ScrollUnit) {
+    match delta {
+        MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64, ScrollUnit::Lines),
+        MouseScrollDelta::PixelDelta(position) => (position.x, position.y, ScrollUnit::Pixels),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MouseScrolledEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
+    pub device_id: DeviceId,
+    pub x_offset: f64,
+    pub y_offset: f64,
+    pub unit: ScrollUnit,
+    pub phase: TouchPhase,
+}
+
+impl Eventable for MouseScrolledEvent {
+    crate::serializable_event!("MouseScrolledEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::MouseCategory
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "{}: x- {}, y- {} ({:?})",
+            self.name, self.x_offset, self.y_offset, self.unit
+        )
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+impl MouseScrolledEvent {
+    pub fn from_delta(
+        window_id: WindowId,
+        device_id: DeviceId,
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+    ) -> Self {
+        let (x_offset, y_offset, unit) = scroll_delta_to_offset(delta);
+
+        Self {
+            name: String::from("MouseScrolledEvent"),
+            is_handled: false,
+            window_id,
+            device_id,
+            x_offset,
+            y_offset,
+            unit,
+            phase,
+        }
+    }
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+    pub fn get_offset(&self) -> (f64, f64) {
+        (self.x_offset, self.y_offset)
+    }
+    pub fn get_unit(&self) -> ScrollUnit {
+        self.unit
+    }
+    pub fn get_phase(&self) -> TouchPhase {
+        self.phase
+    }
+}
+
+//Cursor Entered / Left Events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorEnteredEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
+    pub device_id: DeviceId,
+}
+
+impl Eventable for CursorEnteredEvent {
+    crate::serializable_event!("CursorEnteredEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::MouseCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+impl CursorEnteredEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorLeftEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
+    pub device_id: DeviceId,
+}
+
+impl Eventable for CursorLeftEvent {
+    crate::serializable_event!("CursorLeftEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory | EventCategory::MouseCategory
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+impl CursorLeftEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+}
+
+//Touch Event
+/// One finger of a touch screen starting, moving, lifting or being cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TouchEvent {
+    pub name: String,
+    #[serde(skip)]
+    pub is_handled: bool,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    #[serde(skip, default = "DeviceId::dummy")]
+    pub device_id: DeviceId,
+    /// Stays the same for one finger from `Started` until `Ended` or `Cancelled`.
+    pub finger_id: u64,
+    pub phase: TouchPhase,
+    pub x: f64,
+    pub y: f64,
+    /// Normalized pressure in `0.0..=1.0`, if the device reports it.
+    pub force: Option<f64>,
+}
+
+impl Eventable for TouchEvent {
+    crate::serializable_event!("TouchEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::InputCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "{}: finger - {}, {:?}, x- {}, y- {}",
+            self.name, self.finger_id, self.phase, self.x, self.y
+        )
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+impl TouchEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+    pub fn get_finger_id(&self) -> u64 {
+        self.finger_id
+    }
+    pub fn get_phase(&self) -> TouchPhase {
+        self.phase
+    }
+    pub fn get_pos(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+    pub fn get_force(&self) -> Option<f64> {
+        self.force
+    }
+}
@@ -1,20 +1,38 @@
-use winit::{event::WindowEvent, window::WindowId};
+use std::{path::PathBuf, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::WindowEvent,
+    window::{Window, WindowId},
+};
 
 impl Eventable for WindowEvent {
+    fn get_type_name(&self) -> &'static str {
+        "WindowEvent"
+    }
     fn get_name(&self) -> &str {
         "Window Event"
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
 
     fn is_handled(&self) -> bool {
         false
     }
+
+    /// Raw winit events carry no handled flag, so they are always delivered to every layer.
+    fn set_handled(&mut self, _is_handled: bool) {}
 }
 
-use super::Eventable;
+use super::{EventCategory, EventCategoryFlags, Eventable};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowCloseEvent {
     pub name: String,
+    #[serde(with = "super::window_id_serde")]
     pub window_id: WindowId,
+    #[serde(skip)]
     pub is_handled: bool,
 }
 
@@ -25,28 +43,43 @@ impl WindowCloseEvent {
 }
 
 impl Eventable for WindowCloseEvent {
+    crate::serializable_event!("WindowCloseEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
 
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowResizeEvent {
     pub name: String,
+    #[serde(with = "super::window_id_serde")]
     pub window_id: WindowId,
     pub width: u32,
     pub height: u32,
+    #[serde(skip)]
     pub is_handled: bool,
 }
 
 impl Eventable for WindowResizeEvent {
+    crate::serializable_event!("WindowResizeEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
     fn to_string(&self) -> String {
         format!(
             "{}: width - {}, height - {}",
@@ -57,32 +90,48 @@ impl Eventable for WindowResizeEvent {
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 
 impl WindowResizeEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
     pub fn get_width_and_height(&self) -> (u32, u32) {
         (self.width, self.height)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowRedrawRequestedEvent {
     pub name: String,
+    #[serde(with = "super::window_id_serde")]
     pub window_id: WindowId,
+    #[serde(skip)]
     pub is_handled: bool,
 }
 
 impl Eventable for WindowRedrawRequestedEvent {
+    crate::serializable_event!("WindowRedrawRequestedEvent");
+
     fn get_name(&self) -> &str {
         &self.name
     }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
     fn to_string(&self) -> String {
-        format!("{}", self.name)
+        self.name.clone()
     }
 
     fn is_handled(&self) -> bool {
         self.is_handled
     }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
 }
 
 impl WindowRedrawRequestedEvent {
@@ -90,3 +139,250 @@ impl WindowRedrawRequestedEvent {
         &self.window_id
     }
 }
+
+/// Sent after a window requested with [`WindowManagerCustomEvent::OpenWindow`](crate::sf_window::WindowManagerCustomEvent::OpenWindow) was created.
+pub struct WindowOpenedEvent {
+    pub name: String,
+    /// The label passed with the open request.
+    pub label: String,
+    pub window: Arc<Window>,
+    pub is_handled: bool,
+}
+
+impl Eventable for WindowOpenedEvent {
+    fn get_type_name(&self) -> &'static str {
+        "WindowOpenedEvent"
+    }
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!("{}: {}", self.name, self.label)
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+impl WindowOpenedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window.id()
+    }
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowFocusEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub is_focused: bool,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for WindowFocusEvent {
+    crate::serializable_event!("WindowFocusEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!("{}: focused - {}", self.name, self.is_focused)
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+impl WindowFocusEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+}
+
+/// The outer position of the window changed, in physical pixels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowMovedEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub x: i32,
+    pub y: i32,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for WindowMovedEvent {
+    crate::serializable_event!("WindowMovedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!("{}: x - {}, y - {}", self.name, self.x, self.y)
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+impl WindowMovedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_pos(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+}
+
+/// Sent when the window moved to a monitor with another DPI or the system scale changed.
+/// A [`WindowResizeEvent`] with the new physical size follows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowScaleFactorChangedEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub scale_factor: f64,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for WindowScaleFactorChangedEvent {
+    crate::serializable_event!("WindowScaleFactorChangedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!("{}: scale factor - {}", self.name, self.scale_factor)
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+impl WindowScaleFactorChangedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+}
+
+/// The window became fully hidden (e.g. minimized or covered) or visible again.
+/// Rendering can be skipped while it is occluded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowOccludedEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub is_occluded: bool,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for WindowOccludedEvent {
+    crate::serializable_event!("WindowOccludedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!("{}: occluded - {}", self.name, self.is_occluded)
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+impl WindowOccludedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn is_occluded(&self) -> bool {
+        self.is_occluded
+    }
+}
+
+/// A file was dragged onto the window and dropped, sent once per file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDroppedEvent {
+    pub name: String,
+    #[serde(with = "super::window_id_serde")]
+    pub window_id: WindowId,
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub is_handled: bool,
+}
+
+impl Eventable for FileDroppedEvent {
+    crate::serializable_event!("FileDroppedEvent");
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_category_flags(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
+    fn to_string(&self) -> String {
+        format!("{}: {}", self.name, self.path.display())
+    }
+
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+impl FileDroppedEvent {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window_id
+    }
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+}
@@ -0,0 +1,12 @@
+//! Serializes a [`WindowId`] as its `u64` value, for `#[serde(with = "...")]`.
+
+use serde::{Deserialize, Deserializer, Serializer};
+use winit::window::WindowId;
+
+pub fn serialize<S: Serializer>(window_id: &WindowId, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::from(*window_id))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<WindowId, D::Error> {
+    u64::deserialize(deserializer).map(WindowId::from)
+}
@@ -0,0 +1,206 @@
+pub mod pipeline_cache;
+pub use pipeline_cache::*;
+pub mod shader;
+pub use shader::*;
+
+use std::rc::Rc;
+
+use glam::Vec4;
+use wgpu::util::DeviceExt;
+
+use crate::core::world::VertexLayout;
+
+use super::texture::Texture;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    /// Blended over what is behind it using the alpha of the output color, doesn't write depth.
+    AlphaBlend,
+    /// Added on top of what is behind it, doesn't write depth.
+    Additive,
+}
+
+impl BlendMode {
+    pub fn get_blend_state(&self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            BlendMode::AlphaBlend => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        *self != BlendMode::Opaque
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialParams {
+    /// Linear RGBA, multiplied with the texture color.
+    pub base_color: Vec4,
+    pub roughness: f32,
+    pub metallic: f32,
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            roughness: 0.5,
+            metallic: 0.0,
+        }
+    }
+}
+
+/// Material parameters bound at group 1 of material shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniform {
+    pub base_color: [f32; 4],
+    pub roughness: f32,
+    pub metallic: f32,
+    _padding: [f32; 2],
+}
+
+impl MaterialUniform {
+    pub fn from_params(params: &MaterialParams) -> Self {
+        Self {
+            base_color: params.base_color.to_array(),
+            roughness: params.roughness,
+            metallic: params.metallic,
+            _padding: [0.0; 2],
+        }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Material bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+}
+
+/// Everything needed to create a [`Material`], see [`World::create_material`](crate::core::world::World::create_material).
+#[derive(Clone)]
+pub struct MaterialDescriptor {
+    pub shader: ShaderId,
+    pub params: MaterialParams,
+    /// Sampled at group 2, a white texture is bound when `None`.
+    pub texture: Option<Rc<Texture>>,
+    pub blend_mode: BlendMode,
+    pub cull_mode: Option<wgpu::Face>,
+}
+
+impl MaterialDescriptor {
+    pub fn new(shader: ShaderId) -> Self {
+        Self {
+            shader,
+            params: MaterialParams::default(),
+            texture: None,
+            blend_mode: BlendMode::Opaque,
+            cull_mode: Some(wgpu::Face::Back),
+        }
+    }
+}
+
+/// How a [`Mesh`](crate::core::world::Mesh) is shaded. Used as a component next to the mesh.
+///
+/// GPU resources are reference counted by wgpu, so clones share the same parameters buffer
+/// and [`Material::set_params`] affects every clone.
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub shader: ShaderId,
+    pub blend_mode: BlendMode,
+    pub cull_mode: Option<wgpu::Face>,
+    pub texture: Option<Rc<Texture>>,
+    params: MaterialParams,
+    params_buffer: wgpu::Buffer,
+    pub params_bind_group: wgpu::BindGroup,
+    pub texture_bind_group: wgpu::BindGroup,
+}
+
+impl Material {
+    /// `fallback_texture` is bound when the descriptor has no texture.
+    pub fn new(
+        device: &wgpu::Device,
+        params_layout: &wgpu::BindGroupLayout,
+        texture_layout: &wgpu::BindGroupLayout,
+        fallback_texture: &Texture,
+        descriptor: MaterialDescriptor,
+    ) -> Self {
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material buffer"),
+            contents: bytemuck::cast_slice(&[MaterialUniform::from_params(&descriptor.params)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Material bind group"),
+            layout: params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+        let texture_bind_group = descriptor
+            .texture
+            .as_deref()
+            .unwrap_or(fallback_texture)
+            .create_bind_group(device, texture_layout);
+
+        Self {
+            shader: descriptor.shader,
+            blend_mode: descriptor.blend_mode,
+            cull_mode: descriptor.cull_mode,
+            texture: descriptor.texture,
+            params: descriptor.params,
+            params_buffer,
+            params_bind_group,
+            texture_bind_group,
+        }
+    }
+
+    pub fn get_params(&self) -> &MaterialParams {
+        &self.params
+    }
+
+    pub fn set_params(&mut self, queue: &wgpu::Queue, params: MaterialParams) {
+        self.params = params;
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[MaterialUniform::from_params(&params)]),
+        );
+    }
+
+    pub fn get_pipeline_key(
+        &self,
+        vertex_layout: VertexLayout,
+        target_format: wgpu::TextureFormat,
+    ) -> PipelineKey {
+        PipelineKey {
+            shader: self.shader,
+            blend_mode: self.blend_mode,
+            cull_mode: self.cull_mode,
+            vertex_layout,
+            target_format,
+        }
+    }
+}
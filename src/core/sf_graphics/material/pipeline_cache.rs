@@ -0,0 +1,154 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::core::{
+    sf_graphics::{texture::DepthTexture, world_graphics::InstanceRaw},
+    world::VertexLayout,
+};
+
+use super::{BlendMode, ShaderId, ShaderLibrary};
+
+/// Everything a world render pipeline depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: ShaderId,
+    pub blend_mode: BlendMode,
+    pub cull_mode: Option<wgpu::Face>,
+    pub vertex_layout: VertexLayout,
+    pub target_format: wgpu::TextureFormat,
+}
+
+/// Creates world render pipelines on first use and keeps them for the following frames.
+pub struct PipelineCache {
+    layout: wgpu::PipelineLayout,
+    pipelines: RefCell<HashMap<PipelineKey, wgpu::RenderPipeline>>,
+}
+
+impl PipelineCache {
+    /// Every cached pipeline shares the same `bind_group_layouts`.
+    pub fn new(device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout]) -> Self {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("World pipeline layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            layout,
+            pipelines: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `None` if the key references a shader missing from `shaders`.
+    pub fn get_or_create(
+        &self,
+        device: &wgpu::Device,
+        shaders: &ShaderLibrary,
+        key: PipelineKey,
+    ) -> Option<wgpu::RenderPipeline> {
+        if let Some(pipeline) = self.pipelines.borrow().get(&key) {
+            return Some(pipeline.clone());
+        }
+
+        let shader = shaders.get(key.shader)?;
+        let pipeline = self.create_pipeline(device, &shader.name, &shader.module, &key);
+        self.pipelines.borrow_mut().insert(key, pipeline.clone());
+        Some(pipeline)
+    }
+
+    /// Builds new versions of every cached pipeline using `shader`, without replacing them yet.
+    pub fn create_pipelines_for_shader(
+        &self,
+        device: &wgpu::Device,
+        shader: ShaderId,
+        label: &str,
+        module: &wgpu::ShaderModule,
+    ) -> Vec<(PipelineKey, wgpu::RenderPipeline)> {
+        let keys: Vec<PipelineKey> = self
+            .pipelines
+            .borrow()
+            .keys()
+            .filter(|key| key.shader == shader)
+            .copied()
+            .collect();
+
+        keys.into_iter()
+            .map(|key| (key, self.create_pipeline(device, label, module, &key)))
+            .collect()
+    }
+
+    pub fn insert_pipelines(
+        &self,
+        pipelines: impl IntoIterator<Item = (PipelineKey, wgpu::RenderPipeline)>,
+    ) {
+        self.pipelines.borrow_mut().extend(pipelines);
+    }
+
+    /// Drops every pipeline built from the shader, they are recreated on next use.
+    pub fn invalidate_shader(&self, shader: ShaderId) {
+        self.pipelines
+            .borrow_mut()
+            .retain(|key, _| key.shader != shader);
+    }
+
+    pub fn clear(&self) {
+        self.pipelines.borrow_mut().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.borrow().is_empty()
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        module: &wgpu::ShaderModule,
+        key: &PipelineKey,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: Some("vs_main"),
+                buffers: &[key.vertex_layout.get_buffer_layout(), InstanceRaw::layout()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: key.target_format,
+                    blend: Some(key.blend_mode.get_blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                cull_mode: key.cull_mode,
+                front_face: wgpu::FrontFace::Ccw,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                // Transparent surfaces are drawn last and must not hide each other.
+                depth_write_enabled: !key.blend_mode.is_transparent(),
+                ..DepthTexture::depth_stencil_state()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+}
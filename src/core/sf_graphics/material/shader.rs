@@ -0,0 +1,423 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use wgpu::naga;
+
+use crate::{
+    core::sf_error::{SfError, SfResult},
+    error_core,
+};
+
+/// Index of a shader inside a [`ShaderLibrary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+pub struct Shader {
+    pub name: String,
+    pub module: wgpu::ShaderModule,
+    /// File the shader was loaded from, watched for changes.
+    pub path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+/// New source of a shader whose file changed on disk, already validated.
+pub struct ShaderChange {
+    pub id: ShaderId,
+    pub source: String,
+}
+
+/// Compiled shader modules shared by materials.
+///
+/// Shaders used with the world renderer take the camera at group 0, [`MaterialUniform`](super::MaterialUniform)
+/// at group 1 and a texture with its sampler at group 2, with `vs_main` and `fs_main` entry points.
+pub struct ShaderLibrary {
+    shaders: Vec<Shader>,
+    ids_by_name: HashMap<String, ShaderId>,
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+    /// What shaders may use, see [`ShaderLibrary::get_capabilities`].
+    capabilities: naga::valid::Capabilities,
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self {
+            shaders: Vec::new(),
+            ids_by_name: HashMap::new(),
+            poll_interval: Duration::from_millis(500),
+            last_poll: None,
+            capabilities: naga::valid::Capabilities::empty(),
+        }
+    }
+}
+
+impl ShaderLibrary {
+    /// Validates against the WGSL every device supports, see [`ShaderLibrary::with_capabilities`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capabilities(mut self, capabilities: naga::valid::Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// The optional shader features a device supports, matching what wgpu itself validates against.
+    pub fn get_capabilities(
+        features: wgpu::Features,
+        downlevel_flags: wgpu::DownlevelFlags,
+    ) -> naga::valid::Capabilities {
+        use naga::valid::Capabilities;
+        use wgpu::{DownlevelFlags, Features};
+
+        let mut capabilities = Capabilities::empty();
+        for (capability, supported) in [
+            (
+                Capabilities::PUSH_CONSTANT,
+                features.contains(Features::PUSH_CONSTANTS),
+            ),
+            (
+                Capabilities::FLOAT64,
+                features.contains(Features::SHADER_F64),
+            ),
+            (
+                Capabilities::SHADER_FLOAT16,
+                features.contains(Features::SHADER_F16),
+            ),
+            (
+                Capabilities::PRIMITIVE_INDEX,
+                features.contains(Features::SHADER_PRIMITIVE_INDEX),
+            ),
+            (
+                Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+                features.contains(
+                    Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+                ),
+            ),
+            (
+                Capabilities::STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+                features.contains(Features::STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING),
+            ),
+            (
+                Capabilities::UNIFORM_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+                features.contains(Features::UNIFORM_BUFFER_BINDING_ARRAYS),
+            ),
+            (
+                Capabilities::SAMPLER_NON_UNIFORM_INDEXING,
+                features.contains(
+                    Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+                ),
+            ),
+            (
+                Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS,
+                features.contains(Features::TEXTURE_FORMAT_16BIT_NORM),
+            ),
+            (
+                Capabilities::MULTIVIEW,
+                features.contains(Features::MULTIVIEW),
+            ),
+            (
+                Capabilities::EARLY_DEPTH_TEST,
+                features.contains(Features::SHADER_EARLY_DEPTH_TEST),
+            ),
+            (
+                Capabilities::SHADER_INT64,
+                features.contains(Features::SHADER_INT64),
+            ),
+            (
+                Capabilities::SHADER_INT64_ATOMIC_MIN_MAX,
+                features.intersects(
+                    Features::SHADER_INT64_ATOMIC_MIN_MAX | Features::SHADER_INT64_ATOMIC_ALL_OPS,
+                ),
+            ),
+            (
+                Capabilities::SHADER_INT64_ATOMIC_ALL_OPS,
+                features.contains(Features::SHADER_INT64_ATOMIC_ALL_OPS),
+            ),
+            (
+                Capabilities::TEXTURE_ATOMIC,
+                features.contains(Features::TEXTURE_ATOMIC),
+            ),
+            (
+                Capabilities::TEXTURE_INT64_ATOMIC,
+                features.contains(Features::TEXTURE_INT64_ATOMIC),
+            ),
+            (
+                Capabilities::SHADER_FLOAT32_ATOMIC,
+                features.contains(Features::SHADER_FLOAT32_ATOMIC),
+            ),
+            (
+                Capabilities::MULTISAMPLED_SHADING,
+                downlevel_flags.contains(DownlevelFlags::MULTISAMPLED_SHADING),
+            ),
+            (
+                Capabilities::DUAL_SOURCE_BLENDING,
+                features.contains(Features::DUAL_SOURCE_BLENDING),
+            ),
+            (
+                Capabilities::CUBE_ARRAY_TEXTURES,
+                downlevel_flags.contains(DownlevelFlags::CUBE_ARRAY_TEXTURES),
+            ),
+            (
+                Capabilities::SUBGROUP,
+                features.intersects(Features::SUBGROUP | Features::SUBGROUP_VERTEX),
+            ),
+            (
+                Capabilities::SUBGROUP_BARRIER,
+                features.contains(Features::SUBGROUP_BARRIER),
+            ),
+            (
+                Capabilities::SUBGROUP_VERTEX_STAGE,
+                features.contains(Features::SUBGROUP_VERTEX),
+            ),
+            (
+                Capabilities::RAY_QUERY,
+                features.contains(Features::EXPERIMENTAL_RAY_QUERY),
+            ),
+            (
+                Capabilities::RAY_HIT_VERTEX_POSITION,
+                features.contains(Features::EXPERIMENTAL_RAY_HIT_VERTEX_RETURN),
+            ),
+        ] {
+            capabilities.set(capability, supported);
+        }
+        capabilities
+    }
+
+    /// Compiles WGSL source. Adding a shader under an existing name replaces it but keeps its id,
+    /// use [`World::add_shader`](crate::core::world::World::add_shader) to also drop the pipelines built from it.
+    pub fn add_wgsl(&mut self, device: &wgpu::Device, name: &str, source: &str) -> ShaderId {
+        let module = Self::create_module(device, name, source);
+        self.insert(Shader {
+            name: name.to_string(),
+            module,
+            path: None,
+            modified: None,
+        })
+    }
+
+    /// Loads and validates a WGSL file, which is then watched by [`ShaderLibrary::poll_changes`].
+    /// Replaces shaders like [`ShaderLibrary::add_wgsl`], see [`World::load_shader`](crate::core::world::World::load_shader).
+    pub fn load_wgsl(
+        &mut self,
+        device: &wgpu::Device,
+        name: &str,
+        path: impl AsRef<Path>,
+    ) -> SfResult<ShaderId> {
+        let path = path.as_ref();
+        let modified = Self::get_modified(path);
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| SfError::ShaderReadFailed(path.to_path_buf(), e))?;
+        self.validate_wgsl(&source)
+            .map_err(|e| SfError::ShaderInvalid(name.to_string(), e))?;
+
+        let module = Self::create_module(device, name, &source);
+        Ok(self.insert(Shader {
+            name: name.to_string(),
+            module,
+            path: Some(path.to_path_buf()),
+            modified,
+        }))
+    }
+
+    pub fn get(&self, id: ShaderId) -> Option<&Shader> {
+        self.shaders.get(id.0)
+    }
+
+    pub fn find(&self, name: &str) -> Option<ShaderId> {
+        self.ids_by_name.get(name).copied()
+    }
+
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Checks the files of loaded shaders at most once per poll interval and returns the changed ones.
+    /// Sources that fail validation are logged and skipped until the file changes again.
+    pub fn poll_changes(&mut self) -> Vec<ShaderChange> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last_poll| now.duration_since(last_poll) < self.poll_interval)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+
+        let capabilities = self.capabilities;
+        let mut changes = Vec::new();
+        for (index, shader) in self.shaders.iter_mut().enumerate() {
+            let Some(path) = &shader.path else {
+                continue;
+            };
+            let modified = Self::get_modified(path);
+            if modified.is_none() || modified == shader.modified {
+                continue;
+            }
+            shader.modified = modified;
+
+            let source = match std::fs::read_to_string(path) {
+                Ok(source) => source,
+                Err(e) => {
+                    error_core!("failed to read shader {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if let Err(e) = Self::validate(&source, capabilities) {
+                error_core!(
+                    "shader {} failed to compile, keeping the previous version:\n{}",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+
+            changes.push(ShaderChange {
+                id: ShaderId(index),
+                source,
+            });
+        }
+        changes
+    }
+
+    /// Swaps the module of an existing shader, e.g. after a reload.
+    pub fn replace_module(&mut self, id: ShaderId, module: wgpu::ShaderModule) {
+        if let Some(shader) = self.shaders.get_mut(id.0) {
+            shader.module = module;
+        }
+    }
+
+    /// Parses and validates WGSL with naga, returning the formatted error on failure.
+    pub fn validate_wgsl(&self, source: &str) -> Result<(), String> {
+        Self::validate(source, self.capabilities)
+    }
+
+    fn validate(source: &str, capabilities: naga::valid::Capabilities) -> Result<(), String> {
+        let module = naga::front::wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .validate(&module)
+            .map_err(|e| e.emit_to_string(source))?;
+        Ok(())
+    }
+
+    pub fn create_module(device: &wgpu::Device, name: &str, source: &str) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        })
+    }
+
+    fn insert(&mut self, shader: Shader) -> ShaderId {
+        if let Some(&id) = self.ids_by_name.get(&shader.name) {
+            self.shaders[id.0] = shader;
+            return id;
+        }
+
+        let id = ShaderId(self.shaders.len());
+        self.ids_by_name.insert(shader.name.clone(), id);
+        self.shaders.push(shader);
+        id
+    }
+
+    fn get_modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+    use crate::core::sf_graphics::wgpu_backend::WgpuGraphics;
+
+    const VALID: &str =
+        "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }";
+
+    #[test]
+    fn validates_wgsl() {
+        let shaders = ShaderLibrary::new();
+        shaders.validate_wgsl(VALID).unwrap();
+        shaders
+            .validate_wgsl(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/resources/shaders/shader.wgsl"
+            )))
+            .unwrap();
+
+        let syntax_error = shaders.validate_wgsl("fn broken( {").unwrap_err();
+        assert!(syntax_error.contains("error"), "{}", syntax_error);
+        // Parses, but returns the wrong type.
+        let type_error = shaders
+            .validate_wgsl("fn one() -> f32 { return vec2<f32>(1.0); }")
+            .unwrap_err();
+        assert!(type_error.contains("one"), "{}", type_error);
+    }
+
+    #[test]
+    fn validates_against_the_device_capabilities() {
+        let uses_f64 = "fn one() -> f64 { return 1.0lf; }";
+
+        assert!(ShaderLibrary::new().validate_wgsl(uses_f64).is_err());
+        let capabilities = ShaderLibrary::get_capabilities(
+            wgpu::Features::SHADER_F64,
+            wgpu::DownlevelFlags::all(),
+        );
+        ShaderLibrary::new()
+            .with_capabilities(capabilities)
+            .validate_wgsl(uses_f64)
+            .unwrap();
+    }
+
+    #[test]
+    fn polls_changed_shader_files() {
+        let Some(graphics) = WgpuGraphics::headless_for_tests(4) else {
+            return;
+        };
+        let path = std::env::temp_dir().join(format!("strife-shader-{}.wgsl", std::process::id()));
+        let write = |source: &str, seconds: u64| {
+            std::fs::write(&path, source).unwrap();
+            // Set explicitly, writes within the file system's timestamp resolution look unchanged.
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+                .unwrap();
+        };
+
+        write(VALID, 1);
+        let mut shaders = ShaderLibrary::new();
+        shaders.set_poll_interval(Duration::ZERO);
+        let id = shaders.load_wgsl(&graphics.device, "test", &path).unwrap();
+        assert!(shaders.poll_changes().is_empty());
+
+        write("fn broken( {", 2);
+        assert!(shaders.poll_changes().is_empty());
+        // Skipped until the file changes again.
+        assert!(shaders.poll_changes().is_empty());
+
+        let fixed = format!("{}\nfn unused() {{}}", VALID);
+        write(&fixed, 3);
+        let changes = shaders.poll_changes();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].id, id);
+        assert_eq!(changes[0].source, fixed);
+    }
+
+    #[test]
+    fn poll_interval_limits_how_often_files_are_checked() {
+        let mut shaders = ShaderLibrary::new();
+        shaders.set_poll_interval(Duration::from_secs(3600));
+
+        assert!(shaders.poll_changes().is_empty());
+        assert!(shaders.last_poll.is_some());
+        let last_poll = shaders.last_poll;
+        shaders.poll_changes();
+        assert_eq!(shaders.last_poll, last_poll);
+    }
+}
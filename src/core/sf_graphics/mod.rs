@@ -1,2 +1,4 @@
+pub mod material;
+pub mod texture;
 pub mod wgpu_backend;
 pub mod world_graphics;
@@ -0,0 +1,258 @@
+use std::path::Path;
+
+use crate::core::sf_error::{SfError, SfResult};
+
+/// Depth attachment matching one color render target. Has to be recreated whenever the target is resized.
+pub struct DepthTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl DepthTexture {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    pub fn new(device: &wgpu::Device, size: (u32, u32)) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth texture"),
+            size: wgpu::Extent3d {
+                width: size.0.max(1),
+                height: size.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self { texture, view }
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+
+    pub fn depth_stencil_state() -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: Self::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+/// Sampled color texture with a full mip chain, bound to the world shader through [`Texture::create_bind_group`].
+#[derive(Debug)]
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Loads a PNG or JPEG file.
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> SfResult<Self> {
+        let path = path.as_ref();
+        let image =
+            image::open(path).map_err(|e| SfError::ImageLoadFailed(path.to_path_buf(), e))?;
+        Self::from_image(device, queue, &image, path.to_str())
+    }
+
+    /// Decodes an in memory PNG or JPEG file.
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> SfResult<Self> {
+        let image = image::load_from_memory(bytes).map_err(SfError::ImageDecodeFailed)?;
+        Self::from_image(device, queue, &image, label)
+    }
+
+    /// Uploads the image and every mip level, downscaled on the CPU.
+    /// Fails for images larger than the device supports.
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> SfResult<Self> {
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        if image.width() > max_dimension || image.height() > max_dimension {
+            return Err(SfError::TextureTooLarge(
+                image.width(),
+                image.height(),
+                max_dimension,
+            ));
+        }
+
+        Ok(Self::upload(device, queue, image.to_rgba8(), label))
+    }
+
+    fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mut level: image::RgbaImage,
+        label: Option<&str>,
+    ) -> Self {
+        let (width, height) = level.dimensions();
+        let mip_level_count = Self::get_mip_level_count(width, height);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        for mip_level in 0..mip_level_count {
+            if mip_level > 0 {
+                level = image::imageops::resize(
+                    &level,
+                    (level.width() / 2).max(1),
+                    (level.height() / 2).max(1),
+                    image::imageops::FilterType::Triangle,
+                );
+            }
+
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &level,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * level.width()),
+                    rows_per_image: Some(level.height()),
+                },
+                wgpu::Extent3d {
+                    width: level.width(),
+                    height: level.height(),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// 1x1 texture of a single sRGB color, e.g. white for untextured meshes.
+    pub fn from_color(device: &wgpu::Device, queue: &wgpu::Queue, color: [u8; 4]) -> Self {
+        let image = image::RgbaImage::from_pixel(1, 1, image::Rgba(color));
+        Self::upload(device, queue, image, Some("Color texture"))
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+
+    pub fn get_mip_level_count(width: u32, height: u32) -> u32 {
+        width.max(height).max(1).ilog2() + 1
+    }
+
+    /// Texture at binding 0 and its sampler at binding 1, visible to the fragment stage.
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sf_graphics::wgpu_backend::WgpuGraphics;
+
+    #[test]
+    fn images_larger_than_the_device_limit_are_rejected() {
+        let Some(graphics) = WgpuGraphics::headless_for_tests(4) else {
+            return;
+        };
+        let max_dimension = graphics.device.limits().max_texture_dimension_2d;
+        let image = image::DynamicImage::new_rgba8(max_dimension + 1, 1);
+
+        let result = Texture::from_image(&graphics.device, &graphics.queue, &image, None);
+
+        assert!(matches!(
+            result,
+            Err(SfError::TextureTooLarge(width, 1, max)) if width == max_dimension + 1 && max == max_dimension
+        ));
+    }
+}
@@ -1,74 +1,484 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{collections::HashMap, sync::mpsc};
 
-use winit::{dpi::PhysicalSize, window::Window};
+use winit::{dpi::PhysicalSize, window::WindowId};
+
+use crate::{
+    core::{
+        sf_error::{SfError, SfResult},
+        sf_graphics::texture::DepthTexture,
+    },
+    warn_core,
+};
 
 pub struct WgpuGraphics {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
-    pub surface: wgpu::Surface<'static>,
+    /// One surface per open window, see [`WgpuGraphics::add_surface`].
+    pub surfaces: HashMap<WindowId, WindowSurface>,
+    /// Window rendered by [`WgpuGraphics::acquire_surface_texture`], `None` when running headless.
+    pub main_window: Option<WindowId>,
+    /// Describes the main render target. In headless mode no surface is configured
+    /// with it, it only carries the format and size of the offscreen target.
     pub surface_config: wgpu::SurfaceConfiguration,
+    pub offscreen_target: Option<OffscreenTarget>,
+}
+
+/// Surface of one window together with the depth attachment used to render into it.
+pub struct WindowSurface {
+    pub surface: wgpu::Surface<'static>,
+    pub config: wgpu::SurfaceConfiguration,
+    pub depth_texture: DepthTexture,
+}
+
+impl WindowSurface {
+    fn new(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        surface: wgpu::Surface<'static>,
+        size: PhysicalSize<u32>,
+    ) -> SfResult<Self> {
+        let surface_caps = surface.get_capabilities(adapter);
+
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .find(|f| f.is_srgb())
+            .or(surface_caps.formats.first())
+            .copied()
+            .ok_or(SfError::NoSurfaceFormat)?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Immediate,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(device, &config);
+        let depth_texture = DepthTexture::new(device, (config.width, config.height));
+
+        Ok(Self {
+            surface,
+            config,
+            depth_texture,
+        })
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.config.width = size.width;
+            self.config.height = size.height;
+            self.surface.configure(device, &self.config);
+            self.depth_texture = DepthTexture::new(device, (size.width, size.height));
+        }
+    }
+
+    /// See [`WgpuGraphics::acquire_surface_texture`].
+    fn acquire_texture(&self, device: &wgpu::Device) -> SfResult<Option<wgpu::SurfaceTexture>> {
+        match self.surface.get_current_texture() {
+            Ok(surface_texture) => Ok(Some(surface_texture)),
+            Err(e @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                warn_core!("{}, reconfiguring surface", SfError::from(e));
+                self.surface.configure(device, &self.config);
+                Ok(None)
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                warn_core!("{}, skipping frame", SfError::SurfaceTimeout);
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Settings for a [`WgpuGraphics`] that renders without a window.
+pub struct HeadlessConfig {
+    pub size: PhysicalSize<u32>,
+    pub format: wgpu::TextureFormat,
+    /// Allows software adapters (e.g. llvmpipe / WARP) when no hardware adapter is available.
+    pub allow_fallback_adapter: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            size: PhysicalSize::new(256, 256),
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            allow_fallback_adapter: true,
+        }
+    }
+}
+
+/// Texture used instead of a surface when rendering headless.
+pub struct OffscreenTarget {
+    pub texture: wgpu::Texture,
+    pub depth_texture: DepthTexture,
+}
+
+impl OffscreenTarget {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat, size: PhysicalSize<u32>) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen target texture"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let depth_texture = DepthTexture::new(device, (size.width, size.height));
+
+        Self {
+            texture,
+            depth_texture,
+        }
+    }
+
+    pub fn create_view(&self) -> wgpu::TextureView {
+        self.texture
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
 }
 
 impl WgpuGraphics {
+    /// Creates graphics rendering to `surface`, which belongs to the main window.
     pub async fn new(
         instance: wgpu::Instance,
+        main_window: WindowId,
         surface: wgpu::Surface<'static>,
         size: PhysicalSize<u32>,
-    ) -> Self {
+    ) -> SfResult<Self> {
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
             })
-            .await
-            .unwrap();
+            .await?;
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: Some("wgpu device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        let window_surface = WindowSurface::new(&adapter, &device, surface, size)?;
+        let surface_config = window_surface.config.clone();
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            surfaces: HashMap::from([(main_window, window_surface)]),
+            main_window: Some(main_window),
+            surface_config,
+            offscreen_target: None,
+        })
+    }
+
+    /// Creates graphics that render into an [`OffscreenTarget`] instead of a window surface.
+    pub async fn new_headless(instance: wgpu::Instance, config: HeadlessConfig) -> SfResult<Self> {
+        let hardware_adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
             })
-            .await
-            .unwrap();
+            .await;
 
-        let surface_caps = surface.get_capabilities(&adapter);
+        let adapter = match hardware_adapter {
+            Ok(adapter) => adapter,
+            Err(_) if config.allow_fallback_adapter => {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::default(),
+                        compatible_surface: None,
+                        force_fallback_adapter: true,
+                    })
+                    .await?
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(surface_caps.formats[0]);
+        let (device, queue) = Self::request_device(&adapter).await?;
 
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Immediate,
-            alpha_mode: surface_caps.alpha_modes[0],
+            format: config.format,
+            width: config.size.width,
+            height: config.size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
-        surface.configure(&device, &surface_config);
 
-        Self {
+        let offscreen_target = OffscreenTarget::new(&device, config.format, config.size);
+
+        Ok(Self {
+            instance,
+            adapter,
             device,
             queue,
-            surface,
+            surfaces: HashMap::new(),
+            main_window: None,
             surface_config,
+            offscreen_target: Some(offscreen_target),
+        })
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> SfResult<(wgpu::Device, wgpu::Queue)> {
+        let device_and_queue = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("wgpu device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: Default::default(),
+                trace: wgpu::Trace::Off,
+            })
+            .await?;
+
+        Ok(device_and_queue)
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.main_window.is_none()
+    }
+
+    /// Configures a surface for a window, e.g. one opened through the window manager or
+    /// the main window again after a resume.
+    pub fn add_surface(
+        &mut self,
+        window_id: WindowId,
+        surface: wgpu::Surface<'static>,
+        size: PhysicalSize<u32>,
+    ) -> SfResult<()> {
+        let window_surface = WindowSurface::new(&self.adapter, &self.device, surface, size)?;
+        if self.main_window == Some(window_id) {
+            self.surface_config = window_surface.config.clone();
         }
+        self.surfaces.insert(window_id, window_surface);
+        Ok(())
     }
 
+    /// Drops the surface of a window, which has to happen before the window itself is dropped.
+    pub fn remove_surface(&mut self, window_id: WindowId) -> Option<WindowSurface> {
+        self.surfaces.remove(&window_id)
+    }
+
+    /// Drops every surface, done when the application is suspended.
+    /// Nothing is rendered to a window until its surface is added again.
+    pub fn clear_surfaces(&mut self) {
+        self.surfaces.clear();
+    }
+
+    pub fn get_surface(&self, window_id: WindowId) -> Option<&WindowSurface> {
+        self.surfaces.get(&window_id)
+    }
+
+    /// Resizes the main render target, the main window surface or the offscreen target.
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         if size.width > 0 && size.height > 0 {
             self.surface_config.width = size.width;
             self.surface_config.height = size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+            if let Some(main_window) = self.main_window {
+                self.resize_surface(main_window, size);
+            }
+            if self.offscreen_target.is_some() {
+                self.offscreen_target = Some(OffscreenTarget::new(
+                    &self.device,
+                    self.surface_config.format,
+                    size,
+                ));
+            }
+        }
+    }
+
+    pub fn resize_surface(&mut self, window_id: WindowId, size: PhysicalSize<u32>) {
+        if let Some(window_surface) = self.surfaces.get_mut(&window_id) {
+            window_surface.resize(&self.device, size);
         }
     }
+
+    /// Gets the next texture of the main window surface.
+    ///
+    /// A lost or outdated surface (e.g. after a compositor reset) is reconfigured and
+    /// `Ok(None)` is returned, so the caller just skips the frame. Timeouts skip the frame too.
+    pub fn acquire_surface_texture(&self) -> SfResult<Option<wgpu::SurfaceTexture>> {
+        match self.main_window {
+            Some(main_window) => self.acquire_window_texture(main_window),
+            None => Ok(None),
+        }
+    }
+
+    /// Like [`WgpuGraphics::acquire_surface_texture`] for any window with a surface.
+    pub fn acquire_window_texture(
+        &self,
+        window_id: WindowId,
+    ) -> SfResult<Option<wgpu::SurfaceTexture>> {
+        match self.surfaces.get(&window_id) {
+            Some(window_surface) => window_surface.acquire_texture(&self.device),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads back the offscreen target as tightly packed rows of texels, `None` when not headless.
+    pub fn read_offscreen_pixels(&self) -> SfResult<Option<Vec<u8>>> {
+        self.offscreen_target
+            .as_ref()
+            .map(|target| self.read_texture_pixels(&target.texture))
+            .transpose()
+    }
+
+    /// Copies a 2D texture into CPU memory, blocking until the GPU is done.
+    /// The texture must have been created with `COPY_SRC` usage.
+    pub fn read_texture_pixels(&self, texture: &wgpu::Texture) -> SfResult<Vec<u8>> {
+        let width = texture.width();
+        let height = texture.height();
+        let texel_size = texture
+            .format()
+            .block_copy_size(None)
+            .ok_or(SfError::UnreadableTextureFormat(texture.format()))?;
+
+        let unpadded_bytes_per_row = width * texel_size;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let buffer_slice = readback_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::Wait)?;
+        receiver
+            .recv()
+            .map_err(|_| wgpu::BufferAsyncError)
+            .flatten()?;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let mapped = buffer_slice.get_mapped_range();
+            for row in mapped.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        readback_buffer.unmap();
+
+        Ok(pixels)
+    }
+}
+
+#[cfg(test)]
+impl WgpuGraphics {
+    /// Headless graphics rendering into a `size` x `size` target, `None` on machines
+    /// without an adapter so GPU tests can skip themselves.
+    pub(crate) fn headless_for_tests(size: u32) -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let config = HeadlessConfig {
+            size: PhysicalSize::new(size, size),
+            ..HeadlessConfig::default()
+        };
+        match pollster::block_on(Self::new_headless(instance, config)) {
+            Ok(graphics) => Some(graphics),
+            Err(SfError::AdapterNotFound(e)) => {
+                eprintln!("skipping, no graphics adapter: {}", e);
+                None
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::core::{
+        sf_graphics::world_graphics,
+        world::{Mesh, Transform, Vertex, World},
+    };
+
+    const SIZE: u32 = 64;
+
+    #[test]
+    fn renders_world_headless_and_reads_it_back() {
+        let Some(graphics) = WgpuGraphics::headless_for_tests(SIZE) else {
+            return;
+        };
+        let graphics = Rc::new(RefCell::new(graphics));
+        let mut world = World::new(graphics.clone());
+
+        // Without a camera positions are clip space, so this covers the center but not the corners.
+        let triangle = Mesh::new(
+            &graphics.borrow().device,
+            &[
+                Vertex::from_position([0.0, 0.5, 0.0]),
+                Vertex::from_position([-0.5, -0.5, 0.0]),
+                Vertex::from_position([0.5, -0.5, 0.0]),
+            ],
+            &[0, 1, 2],
+        );
+        let entity = world.spawn();
+        world.insert(entity, Transform::default());
+        world.insert(entity, triangle);
+
+        let graphics = graphics.borrow();
+        let target = graphics.offscreen_target.as_ref().unwrap();
+        world_graphics::render_world_to_texture(
+            &world,
+            &target.create_view(),
+            target.texture.format(),
+            &target.depth_texture,
+            None,
+            &graphics,
+        );
+        let pixels = graphics.read_offscreen_pixels().unwrap().unwrap();
+
+        assert_eq!(pixels.len(), (SIZE * SIZE * 4) as usize);
+        let pixel = |x: u32, y: u32| {
+            let start = ((y * SIZE + x) * 4) as usize;
+            &pixels[start..start + 4]
+        };
+        assert_eq!(pixel(SIZE / 2, SIZE / 2), [255, 255, 255, 255]);
+        assert_ne!(pixel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(0, 0), pixel(SIZE - 1, SIZE - 1));
+    }
 }
@@ -1,16 +1,189 @@
-use std::{cell::RefCell, rc::Rc};
+use std::mem;
 
-use crate::core::world::World;
+use wgpu::util::DeviceExt;
 
-use super::wgpu_backend::WgpuGraphics;
+use glam::Mat4;
 
-const VERTEX_BUFFER: [[f32; 3]; 3] = [[0.0, 0.5, 0.0], [-0.5, -0.5, 0.0], [0.5, -0.5, 0.0]];
+use crate::core::world::{Camera, Mesh, Transform, World};
 
+use super::{material::Material, texture::DepthTexture, wgpu_backend::WgpuGraphics};
+
+/// Per instance data of a drawn entity, fed to the world shader as a second vertex buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+}
+
+impl InstanceRaw {
+    pub fn from_transform(transform: &Transform) -> Self {
+        Self {
+            model: transform.get_matrix().to_cols_array_2d(),
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Camera data bound at group 0 of the world shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view_projection: [[f32; 4]; 4],
+    pub position: [f32; 4],
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self {
+            view_projection: Mat4::IDENTITY.to_cols_array_2d(),
+            position: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl CameraUniform {
+    pub fn from_camera(camera: &Camera, transform: &Transform) -> Self {
+        Self {
+            view_projection: camera
+                .get_view_projection_matrix(transform)
+                .to_cols_array_2d(),
+            position: transform.position.extend(1.0).to_array(),
+        }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+}
+
+struct Draw<'w> {
+    mesh: &'w Mesh,
+    material: &'w Material,
+    pipeline: wgpu::RenderPipeline,
+    instance: u32,
+    camera_distance: f32,
+}
+
+/// Draws every entity that has both a [`Transform`] and a [`Mesh`] as seen by the active [`Camera`],
+/// shaded with its [`Material`] or the world's default one.
+/// Without a camera positions are used as clip space coordinates.
+///
+/// Opaque meshes are drawn first, transparent ones after them from back to front.
+/// `target_format` must be the format of the texture behind `texture_view`
+/// and `depth_texture` must have the same size.
+/// `aspect_ratio` overrides the camera's for this render only, e.g. for a target shaped unlike the world's view.
 pub fn render_world_to_texture(
     world: &World,
     texture_view: &wgpu::TextureView,
+    target_format: wgpu::TextureFormat,
+    depth_texture: &DepthTexture,
+    aspect_ratio: Option<f32>,
     graphics: &WgpuGraphics,
 ) {
+    let active_camera = world.get_active_camera();
+    let camera_uniform = active_camera
+        .map(|(_, camera, transform)| {
+            let camera = Camera {
+                aspect_ratio: aspect_ratio.unwrap_or(camera.aspect_ratio),
+                ..*camera
+            };
+            CameraUniform::from_camera(&camera, transform)
+        })
+        .unwrap_or_default();
+    let camera_position = active_camera
+        .map(|(_, _, transform)| transform.position)
+        .unwrap_or_default();
+    graphics.queue.write_buffer(
+        &world.camera_buffer,
+        0,
+        bytemuck::cast_slice(&[camera_uniform]),
+    );
+
+    let mut draws = Vec::new();
+    let mut instances = Vec::new();
+    for (entity, (transform, mesh)) in world.query::<(&Transform, &Mesh)>() {
+        let material = world
+            .get::<Material>(entity)
+            .unwrap_or(&world.default_material);
+        let key = material.get_pipeline_key(mesh.vertex_layout, target_format);
+        let Some(pipeline) = world
+            .pipelines
+            .get_or_create(&graphics.device, &world.shaders, key)
+        else {
+            continue;
+        };
+
+        draws.push(Draw {
+            mesh,
+            material,
+            pipeline,
+            instance: instances.len() as u32,
+            camera_distance: transform.position.distance_squared(camera_position),
+        });
+        instances.push(InstanceRaw::from_transform(transform));
+    }
+
+    draws.sort_by(|a, b| {
+        let a_transparent = a.material.blend_mode.is_transparent();
+        let b_transparent = b.material.blend_mode.is_transparent();
+        a_transparent.cmp(&b_transparent).then_with(|| {
+            if a_transparent {
+                b.camera_distance.total_cmp(&a.camera_distance)
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+    });
+    let instance_buffer = (!instances.is_empty()).then(|| {
+        graphics
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("World Instance buffer"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+    });
+
     let mut encoder = graphics
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -33,23 +206,38 @@ pub fn render_world_to_texture(
                     store: wgpu::StoreOp::Store,
                 },
             })],
-            // depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-            //     view: &self.depth_texture.view,
-            //     depth_ops: Some(wgpu::Operations {
-            //         load: wgpu::LoadOp::Clear(1.0),
-            //         store: wgpu::StoreOp::Store,
-            //     }),
-            //     stencil_ops: None,
-            // }),
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
             occlusion_query_set: None,
             timestamp_writes: None,
         });
 
-        render_pass.set_vertex_buffer(0, world.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(world.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.set_pipeline(&world.render_pipeline);
-        render_pass.draw_indexed(0..world.num_elements, 0, 0..1);
+        if let Some(instance_buffer) = &instance_buffer {
+            render_pass.set_bind_group(0, &world.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+            for draw in &draws {
+                render_pass.set_pipeline(&draw.pipeline);
+                render_pass.set_bind_group(1, &draw.material.params_bind_group, &[]);
+                render_pass.set_bind_group(2, &draw.material.texture_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, draw.mesh.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(draw.mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                for submesh in &draw.mesh.submeshes {
+                    render_pass.draw_indexed(
+                        submesh.index_range.clone(),
+                        submesh.base_vertex,
+                        draw.instance..draw.instance + 1,
+                    );
+                }
+            }
+        }
     }
 
     graphics.queue.submit(std::iter::once(encoder.finish()));
@@ -0,0 +1,133 @@
+use egui::{CollapsingHeader, ScrollArea};
+
+use crate::core::sf_events::{
+    EventCategory, EventCategoryFlags, EventHandler, EventLog, LoggedEvent,
+};
+
+/// Lists the events of the last frames from an [`EventLog`], newest first. The log is only enabled
+/// while the inspector is shown, so events cost nothing to log while it's collapsed.
+pub struct EventInspector {
+    event_log: EventLog,
+    categories: EventCategoryFlags,
+    /// Only events whose type name contains this are listed.
+    type_filter: String,
+    only_handled: bool,
+}
+
+impl EventInspector {
+    pub fn new(event_log: EventLog) -> Self {
+        event_log.set_enabled(false);
+        Self {
+            event_log,
+            categories: EventCategoryFlags::ALL,
+            type_filter: String::new(),
+            only_handled: false,
+        }
+    }
+
+    /// Shows the inspector in a collapsible window, closed at first.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let is_expanded = egui::Window::new("Events")
+            .default_open(false)
+            .show(ctx, |ui| self.ui(ui))
+            .is_some_and(|response| response.inner.is_some());
+        self.event_log.set_enabled(is_expanded);
+    }
+
+    fn is_shown(&self, event: &LoggedEvent) -> bool {
+        (event.categories.is_empty() || event.categories.intersects(self.categories))
+            && event.type_name.contains(self.type_filter.trim())
+            && (!self.only_handled || event.handled_by.is_some())
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            for category in EventCategory::CATEGORIES {
+                let mut is_shown = self.categories.contains(category);
+                if ui
+                    .checkbox(&mut is_shown, category_label(category))
+                    .changed()
+                {
+                    self.categories.set(category, is_shown);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Type");
+            ui.text_edit_singleline(&mut self.type_filter);
+            ui.checkbox(&mut self.only_handled, "Handled only");
+        });
+        ui.horizontal(|ui| {
+            let mut is_paused = self.event_log.is_paused();
+            if ui.checkbox(&mut is_paused, "Pause").changed() {
+                self.event_log.set_paused(is_paused);
+            }
+            if ui.button("Clear").clicked() {
+                self.event_log.clear();
+            }
+        });
+        ui.separator();
+
+        ScrollArea::vertical().show(ui, |ui| {
+            self.event_log.with_frames(|frames| {
+                for frame in frames.iter().rev() {
+                    let events: Vec<&LoggedEvent> = frame
+                        .events
+                        .iter()
+                        .filter(|event| self.is_shown(event))
+                        .collect();
+                    if events.is_empty() {
+                        continue;
+                    }
+
+                    CollapsingHeader::new(format!(
+                        "Frame {} ({} events)",
+                        frame.index,
+                        events.len()
+                    ))
+                    .id_salt(frame.index)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for event in events {
+                            event_row(ui, event);
+                        }
+                    });
+                }
+            });
+        });
+    }
+}
+
+fn event_row(ui: &mut egui::Ui, event: &LoggedEvent) {
+    let handled_by = match &event.handled_by {
+        Some(EventHandler::Layer(name)) => format!("handled by layer {}", name),
+        Some(EventHandler::Listener {
+            name: Some(name), ..
+        }) => {
+            format!("handled by listener {}", name)
+        }
+        Some(EventHandler::Listener { id, name: None }) => format!("handled by {:?}", id),
+        None => String::from("not handled"),
+    };
+    let details = event.data.as_deref().unwrap_or(&event.description);
+
+    ui.horizontal(|ui| {
+        ui.monospace(event.type_name);
+        ui.weak(handled_by);
+    })
+    .response
+    .on_hover_text(details);
+}
+
+fn category_label(category: EventCategory) -> &'static str {
+    match category {
+        EventCategory::NoCategory => "None",
+        EventCategory::ApplicationCategory => "Application",
+        EventCategory::InputCategory => "Input",
+        EventCategory::KeyboardCategory => "Keyboard",
+        EventCategory::MouseCategory => "Mouse",
+        EventCategory::MouseButtonCategory => "Mouse button",
+        EventCategory::UserCategory => "User",
+        EventCategory::GamepadCategory => "Gamepad",
+    }
+}
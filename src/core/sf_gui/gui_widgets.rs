@@ -1,10 +1,10 @@
 use std::{cell::RefCell, rc::Rc};
 
-use egui::{Vec2, Widget};
-use wgpu::{Extent3d, FilterMode};
+use egui::Vec2;
+use wgpu::FilterMode;
 
 use crate::core::{
-    sf_graphics::{wgpu_backend::WgpuGraphics, world_graphics},
+    sf_graphics::{texture::DepthTexture, wgpu_backend::WgpuGraphics, world_graphics},
     world::World,
 };
 
@@ -12,47 +12,45 @@ pub struct WorldRenderWidget {
     size: (u32, u32),
     graphics: Rc<RefCell<WgpuGraphics>>,
     render_texture: wgpu::Texture,
+    depth_texture: DepthTexture,
 }
 
 impl WorldRenderWidget {
     pub fn new(size: (u32, u32), graphics: Rc<RefCell<WgpuGraphics>>) -> Self {
         let graphics_ref = graphics.borrow();
-        let texture_size = wgpu::Extent3d {
-            width: size.0,
-            height: size.1,
-            depth_or_array_layers: 1,
-        };
-
-        let render_texture = graphics_ref
-            .device
-            .create_texture(&wgpu::TextureDescriptor {
-                label: Some("texture height"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: graphics_ref.surface_config.format,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                    | wgpu::TextureUsages::TEXTURE_BINDING
-                    | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
+        let render_texture = Self::create_render_texture(&graphics_ref, size);
+        let depth_texture = DepthTexture::new(&graphics_ref.device, size);
 
         Self {
             graphics: graphics.clone(),
             size,
             render_texture,
+            depth_texture,
         }
     }
 
+    pub fn get_size(&self) -> (u32, u32) {
+        self.size
+    }
+
     pub fn update_size(&mut self, graphics: &WgpuGraphics, size: (u32, u32)) {
+        if size.0 == 0 || size.1 == 0 {
+            return;
+        }
+
+        self.size = size;
+        self.render_texture = Self::create_render_texture(graphics, size);
+        self.depth_texture = DepthTexture::new(&graphics.device, size);
+    }
+
+    fn create_render_texture(graphics: &WgpuGraphics, size: (u32, u32)) -> wgpu::Texture {
         let texture_size = wgpu::Extent3d {
             width: size.0,
             height: size.1,
             depth_or_array_layers: 1,
         };
 
-        let render_texture = graphics.device.create_texture(&wgpu::TextureDescriptor {
+        graphics.device.create_texture(&wgpu::TextureDescriptor {
             label: Some("texture height"),
             size: texture_size,
             mip_level_count: 1,
@@ -63,15 +61,20 @@ impl WorldRenderWidget {
                 | wgpu::TextureUsages::TEXTURE_BINDING
                 | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
-        });
-
-        self.render_texture = render_texture;
+        })
     }
 
     fn render_world_to_texture(&self, world: &World, texture_view: &wgpu::TextureView) {
         let graphics = self.graphics.borrow();
 
-        world_graphics::render_world_to_texture(world, texture_view, &graphics);
+        world_graphics::render_world_to_texture(
+            world,
+            texture_view,
+            self.render_texture.format(),
+            &self.depth_texture,
+            None,
+            &graphics,
+        );
     }
 
     pub fn ui(
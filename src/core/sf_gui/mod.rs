@@ -1,23 +1,33 @@
+pub mod event_inspector;
 pub mod gui_widgets;
 
 use std::{
-    any::{Any, TypeId},
-    cell::RefCell,
+    any::Any,
+    cell::{Cell, RefCell},
     rc::Rc,
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
-use egui::{CentralPanel, FullOutput, RawInput};
+use egui::{CentralPanel, FullOutput, LayerId, RawInput, Rect};
 use egui_wgpu::{Renderer, ScreenDescriptor};
 use egui_winit::{EventResponse, State, winit::window::Window};
+use event_inspector::EventInspector;
 use gui_widgets::WorldRenderWidget;
-use winit::event::WindowEvent;
+use winit::{dpi::LogicalSize, event::WindowEvent, event_loop::EventLoopProxy};
+
+use crate::{error_core, sf_window::WindowManagerCustomEvent};
 
 use super::{
-    sf_events::{EventDispatcher, EventListener, WindowResizeEvent},
+    sf_error::SfResult,
+    sf_events::{
+        EventDispatcher, EventFamily, EventLog, Eventable, ImeEvent, KeyPressedEvent,
+        KeyReleasedEvent, MouseButtonPressedEvent, MouseButtonReleasedEvent, MouseMoveEvent,
+        MouseScrolledEvent, TextInputEvent, TouchEvent, WindowResizeEvent, WindowScopedEvent,
+    },
     sf_graphics::wgpu_backend::WgpuGraphics,
     sf_layers::Layer,
+    sf_time::Timestep,
     world::World,
 };
 
@@ -28,18 +38,29 @@ pub struct SfGuiLayerWrapper<'a> {
 }
 
 impl<'a> SfGuiLayerWrapper<'a> {
-    pub fn new(name: String, window: Arc<Window>, graphics: Rc<RefCell<WgpuGraphics>>) -> Self {
-        let sf_gui_layer_rc = Rc::new(RefCell::new(SfGuiLayer::new(window, graphics)));
+    pub fn new(
+        name: String,
+        window: Arc<Window>,
+        graphics: Rc<RefCell<WgpuGraphics>>,
+        world: Rc<RefCell<World>>,
+        event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
+        event_log: EventLog,
+    ) -> Self {
+        let sf_gui_layer_rc = Rc::new(RefCell::new(SfGuiLayer::new(
+            window,
+            graphics,
+            world,
+            event_loop_proxy,
+            event_log,
+        )));
         let sf_gui_layer_rc2 = sf_gui_layer_rc.clone();
         let mut event_dispatcher = EventDispatcher::new();
-
-        let window_resize_listener = SfGuiWindowResizeListener {
-            callback: Box::new(move |event| {
-                (*sf_gui_layer_rc2.borrow_mut()).on_resized(event);
+        event_dispatcher
+            .on::<WindowResizeEvent>(move |event| {
+                sf_gui_layer_rc2.borrow_mut().on_resized(event);
                 false
-            }),
-        };
-        event_dispatcher.add_listener(window_resize_listener);
+            })
+            .forget();
 
         Self {
             name,
@@ -55,35 +76,26 @@ impl<'a> Layer for SfGuiLayerWrapper<'a> {
     }
 
     fn on_attach(&mut self) {
-        todo!()
+        self.sf_gui_layer_rc.borrow_mut().on_attach();
     }
 
     fn on_detach(&mut self) {
-        todo!()
+        self.sf_gui_layer_rc.borrow_mut().on_detach();
     }
 
-    fn on_update(&mut self) {
+    fn on_update(&mut self, _timestep: Timestep) {
         self.sf_gui_layer_rc.borrow_mut().on_update();
     }
 
-    fn on_event(&mut self, event: &dyn super::sf_events::Eventable) {
-        if event.type_id() == TypeId::of::<WindowEvent>() {
-            if let Some(e) = (event as &dyn Any).downcast_ref::<WindowEvent>() {
-                self.sf_gui_layer_rc.borrow_mut().on_window_event(e);
-            }
+    fn on_event(&mut self, event: &mut dyn Eventable) {
+        if let Some(e) = (&*event as &dyn Any).downcast_ref::<WindowEvent>() {
+            let _ = self.sf_gui_layer_rc.borrow_mut().on_window_event(e);
         }
         self.event_dispatcher.dispatch_dynamic(event);
-    }
-}
-
-struct SfGuiWindowResizeListener<'a> {
-    callback: Box<dyn Fn(&WindowResizeEvent) -> bool + 'a>,
-}
-impl<'a> EventListener for SfGuiWindowResizeListener<'a> {
-    type EventableConcreteType = WindowResizeEvent;
 
-    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
-        (self.callback)(event)
+        if self.sf_gui_layer_rc.borrow().captures_event(event) {
+            event.set_handled(true);
+        }
     }
 }
 
@@ -100,12 +112,22 @@ struct SfGuiLayer {
     fps_count: u64,
     max_frame_time_for_second: Duration,
     //tmp
-    world: World,
+    world: Rc<RefCell<World>>,
     world_renderer_widget: WorldRenderWidget,
+    /// Where the world was drawn last frame.
+    game_view_rect: Cell<Rect>,
+    event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
+    event_inspector: RefCell<EventInspector>,
 }
 
 impl SfGuiLayer {
-    pub fn new(window: Arc<Window>, graphics: Rc<RefCell<WgpuGraphics>>) -> Self {
+    pub fn new(
+        window: Arc<Window>,
+        graphics: Rc<RefCell<WgpuGraphics>>,
+        world: Rc<RefCell<World>>,
+        event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
+        event_log: EventLog,
+    ) -> Self {
         let egui_context = egui::Context::default();
 
         let egui_winit_state = State::new(
@@ -128,6 +150,8 @@ impl SfGuiLayer {
         )));
 
         let world_renderer_widget = WorldRenderWidget::new((100, 100), graphics.clone());
+        let (width, height) = world_renderer_widget.get_size();
+        world.borrow_mut().set_camera_aspect_ratio(width, height);
 
         Self {
             window,
@@ -140,8 +164,11 @@ impl SfGuiLayer {
             frames_count: 0,
             fps_count: 0,
             max_frame_time_for_second: Duration::new(0, 0),
-            world: World::new(graphics.clone()),
+            world,
             world_renderer_widget,
+            game_view_rect: Cell::new(Rect::NOTHING),
+            event_loop_proxy,
+            event_inspector: RefCell::new(EventInspector::new(event_log)),
         }
     }
 
@@ -152,7 +179,51 @@ impl SfGuiLayer {
         self.egui_winit_state.on_window_event(&self.window, event)
     }
 
+    /// Whether egui is using the input this event represents, e.g. a click that landed on a panel.
+    fn captures_event(&self, event: &dyn Eventable) -> bool {
+        if let Some(event) = <dyn WindowScopedEvent as EventFamily>::from_event(event)
+            && event.get_window_id() != self.window.id()
+        {
+            return false;
+        }
+        let event = event as &dyn Any;
+
+        if event.is::<MouseButtonPressedEvent>()
+            || event.is::<MouseButtonReleasedEvent>()
+            || event.is::<MouseMoveEvent>()
+            || event.is::<MouseScrolledEvent>()
+            || event.is::<TouchEvent>()
+        {
+            return self.egui_context.is_using_pointer()
+                || (self.egui_context.is_pointer_over_area() && !self.is_pointer_over_game_view());
+        }
+        if event.is::<KeyPressedEvent>()
+            || event.is::<KeyReleasedEvent>()
+            || event.is::<TextInputEvent>()
+            || event.is::<ImeEvent>()
+        {
+            return self.egui_context.wants_keyboard_input();
+        }
+
+        false
+    }
+
+    /// The world is shown inside the GUI, so pointer input on its image belongs to the game,
+    /// unless a window is drawn above it.
+    fn is_pointer_over_game_view(&self) -> bool {
+        let Some(pointer_pos) = self.egui_context.input(|i| i.pointer.interact_pos()) else {
+            return false;
+        };
+
+        self.game_view_rect.get().contains(pointer_pos)
+            && self.egui_context.layer_id_at(pointer_pos) == Some(LayerId::background())
+    }
+
     fn on_resized(&mut self, event: &WindowResizeEvent) {
+        if event.get_window_id() != self.window.id() {
+            return;
+        }
+
         self.egui_context
             .set_pixels_per_point(self.window.scale_factor() as f32);
 
@@ -165,6 +236,11 @@ impl SfGuiLayer {
             &graphics,
             ((width as f32 * 0.5) as u32, (height as f32 * 0.5) as u32),
         );
+        // The world is shown in the widget, so cameras follow its texture rather than the window.
+        let (width, height) = self.world_renderer_widget.get_size();
+        self.world
+            .borrow_mut()
+            .set_camera_aspect_ratio(width, height);
     }
 
     fn on_attach(&mut self) {}
@@ -173,7 +249,9 @@ impl SfGuiLayer {
 
     fn on_update(&mut self) {
         self.update_fps();
-        self.update_gui();
+        if let Err(e) = self.update_gui() {
+            error_core!("GUI FRAME FAILED: {}", e);
+        }
     }
 
     fn update_fps(&mut self) {
@@ -195,7 +273,7 @@ impl SfGuiLayer {
         self.last_frame_time = now;
     }
 
-    fn update_gui(&mut self) {
+    fn update_gui(&mut self) -> SfResult<()> {
         let raw_input = self.egui_winit_state.take_egui_input(&self.window);
         let graphics = self.graphics.borrow();
 
@@ -207,40 +285,36 @@ impl SfGuiLayer {
             ],
         };
 
-        let current_texture = graphics
-            .surface
-            .get_current_texture()
-            .expect("egui renderer encoder");
+        let Some(current_texture) = graphics.acquire_surface_texture()? else {
+            return Ok(());
+        };
 
         let view = current_texture
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let (surface_width, surface_height) = (
-            graphics.surface_config.width,
-            graphics.surface_config.height,
-        );
-
-        let texture_width = surface_width as f32 * 0.5;
-        let texture_height = surface_height;
-
         drop(graphics);
 
         self.present_gui_output(screen_descriptor, raw_input, &view);
         current_texture.present();
+
+        Ok(())
     }
 
     fn get_frame_output(&self, raw_input: RawInput) -> FullOutput {
         let fps = self.fps_count;
         let max_frame_time = self.max_frame_time_for_second;
 
-        let full_output = self.egui_context.run(raw_input, |ctx| {
+        self.egui_context.run(raw_input, |ctx| {
             // This is where you define your egui UI
             CentralPanel::default().show(ctx, move |ui| {
                 ui.horizontal(|ui| {
                     ui.vertical(|ui| {
                         ui.heading(format!("FPS: {}", fps));
                         ui.heading(format!("MAX: {:?}", max_frame_time));
+                        if ui.button("Open preview window").clicked() {
+                            self.open_preview_window();
+                        }
                         if ui.button("Click me!").clicked() {
                             println!("Button clicked!");
                         }
@@ -248,8 +322,12 @@ impl SfGuiLayer {
 
                     ui.vertical(|ui| {
                         ui.heading("render");
-                        self.world_renderer_widget
-                            .ui(ui, &self.world, self.egui_renderer.clone())
+                        let response = self.world_renderer_widget.ui(
+                            ui,
+                            &self.world.borrow(),
+                            self.egui_renderer.clone(),
+                        );
+                        self.game_view_rect.set(response.rect);
                     });
 
                     ui.vertical(|ui| {
@@ -260,8 +338,23 @@ impl SfGuiLayer {
                     });
                 });
             });
-        });
-        full_output
+
+            self.event_inspector.borrow_mut().show(ctx);
+        })
+    }
+
+    fn open_preview_window(&self) {
+        let request = WindowManagerCustomEvent::OpenWindow {
+            label: "preview".to_string(),
+            attributes: Box::new(
+                Window::default_attributes()
+                    .with_title("Preview")
+                    .with_inner_size(LogicalSize::new(640, 360)),
+            ),
+        };
+        if self.event_loop_proxy.send_event(request).is_err() {
+            error_core!("can't open preview window, event loop is closed");
+        }
     }
 
     fn present_gui_output(
@@ -303,7 +396,7 @@ impl SfGuiLayer {
             let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("egui render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_view,
+                    view: surface_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
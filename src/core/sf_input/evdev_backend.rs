@@ -0,0 +1,398 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use evdev::{AbsoluteAxisCode, Device, EventSummary, KeyCode};
+
+use crate::info_core;
+
+use super::{GamepadAxis, GamepadButton, GamepadEvent, GamepadId};
+
+const INPUT_DIRECTORY: &str = "/dev/input";
+
+/// Analog triggers count as pressed buttons above this, for pads without digital trigger buttons.
+const TRIGGER_BUTTON_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy)]
+struct AxisRange {
+    minimum: i32,
+    maximum: i32,
+}
+
+impl AxisRange {
+    fn normalize(&self, value: i32) -> f32 {
+        if self.maximum <= self.minimum {
+            return 0.0;
+        }
+        ((value - self.minimum) as f32 / (self.maximum - self.minimum) as f32).clamp(0.0, 1.0)
+    }
+
+    fn normalize_centered(&self, value: i32) -> f32 {
+        self.normalize(value) * 2.0 - 1.0
+    }
+}
+
+fn map_button(code: KeyCode) -> Option<GamepadButton> {
+    let button = match code {
+        KeyCode::BTN_SOUTH | KeyCode::BTN_TRIGGER => GamepadButton::South,
+        KeyCode::BTN_EAST | KeyCode::BTN_THUMB => GamepadButton::East,
+        KeyCode::BTN_WEST | KeyCode::BTN_THUMB2 => GamepadButton::West,
+        KeyCode::BTN_NORTH | KeyCode::BTN_TOP => GamepadButton::North,
+        KeyCode::BTN_TL => GamepadButton::LeftBumper,
+        KeyCode::BTN_TR => GamepadButton::RightBumper,
+        KeyCode::BTN_TL2 => GamepadButton::LeftTrigger,
+        KeyCode::BTN_TR2 => GamepadButton::RightTrigger,
+        KeyCode::BTN_SELECT => GamepadButton::Select,
+        KeyCode::BTN_START => GamepadButton::Start,
+        KeyCode::BTN_MODE => GamepadButton::Mode,
+        KeyCode::BTN_THUMBL => GamepadButton::LeftStick,
+        KeyCode::BTN_THUMBR => GamepadButton::RightStick,
+        KeyCode::BTN_DPAD_UP => GamepadButton::DPadUp,
+        KeyCode::BTN_DPAD_DOWN => GamepadButton::DPadDown,
+        KeyCode::BTN_DPAD_LEFT => GamepadButton::DPadLeft,
+        KeyCode::BTN_DPAD_RIGHT => GamepadButton::DPadRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+/// Gamepads have the standard `BTN_SOUTH` button, joysticks `BTN_TRIGGER`.
+fn is_gamepad(device: &Device) -> bool {
+    device.supported_keys().is_some_and(|keys| {
+        keys.contains(KeyCode::BTN_SOUTH) || keys.contains(KeyCode::BTN_TRIGGER)
+    })
+}
+
+struct EvdevGamepad {
+    id: GamepadId,
+    path: PathBuf,
+    device: Device,
+    axis_ranges: HashMap<AbsoluteAxisCode, AxisRange>,
+    has_trigger_buttons: bool,
+    /// Synthesized [`GamepadButton::LeftTrigger`] and [`GamepadButton::RightTrigger`] state.
+    trigger_buttons_down: [bool; 2],
+    /// Last `ABS_HAT0X` / `ABS_HAT0Y` values, the d-pad of most pads.
+    hat: (i32, i32),
+}
+
+impl EvdevGamepad {
+    fn open(path: &Path, id: GamepadId) -> Option<Self> {
+        let device = Device::open(path).ok()?;
+        if !is_gamepad(&device) {
+            return None;
+        }
+        device.set_nonblocking(true).ok()?;
+
+        let axis_ranges = device
+            .get_absinfo()
+            .map(|absinfo| {
+                absinfo
+                    .map(|(code, info)| {
+                        let range = AxisRange {
+                            minimum: info.minimum(),
+                            maximum: info.maximum(),
+                        };
+                        (code, range)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let has_trigger_buttons = device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(KeyCode::BTN_TL2));
+
+        Some(Self {
+            id,
+            path: path.to_path_buf(),
+            device,
+            axis_ranges,
+            has_trigger_buttons,
+            trigger_buttons_down: [false; 2],
+            hat: (0, 0),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        self.device.name().unwrap_or("Unknown gamepad").to_string()
+    }
+
+    /// Fails once the device is gone.
+    fn read_events(&mut self, events: &mut Vec<GamepadEvent>) -> io::Result<()> {
+        let input_events: Vec<_> = match self.device.fetch_events() {
+            Ok(input_events) => input_events.collect(),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        for input_event in input_events {
+            match input_event.destructure() {
+                EventSummary::Key(_, code, value) => {
+                    // 2 is a key repeat, which gamepads don't need.
+                    if let Some(button) = map_button(code) {
+                        match value {
+                            0 => events.push(GamepadEvent::ButtonReleased(self.id, button)),
+                            1 => events.push(GamepadEvent::ButtonPressed(self.id, button)),
+                            _ => {}
+                        }
+                    }
+                }
+                EventSummary::AbsoluteAxis(_, code, value) => self.on_axis(code, value, events),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn on_axis(&mut self, code: AbsoluteAxisCode, value: i32, events: &mut Vec<GamepadEvent>) {
+        let Some(range) = self.axis_ranges.get(&code).copied() else {
+            return;
+        };
+
+        // evdev reports y growing downwards, the engine uses up as positive.
+        let (axis, value) = match code {
+            AbsoluteAxisCode::ABS_X => (GamepadAxis::LeftStickX, range.normalize_centered(value)),
+            AbsoluteAxisCode::ABS_Y => (GamepadAxis::LeftStickY, -range.normalize_centered(value)),
+            AbsoluteAxisCode::ABS_RX => (GamepadAxis::RightStickX, range.normalize_centered(value)),
+            AbsoluteAxisCode::ABS_RY => {
+                (GamepadAxis::RightStickY, -range.normalize_centered(value))
+            }
+            AbsoluteAxisCode::ABS_Z => (GamepadAxis::LeftTrigger, range.normalize(value)),
+            AbsoluteAxisCode::ABS_RZ => (GamepadAxis::RightTrigger, range.normalize(value)),
+            AbsoluteAxisCode::ABS_HAT0X => {
+                let last = std::mem::replace(&mut self.hat.0, value.signum());
+                self.on_hat(
+                    last,
+                    value.signum(),
+                    GamepadButton::DPadLeft,
+                    GamepadButton::DPadRight,
+                    events,
+                );
+                return;
+            }
+            AbsoluteAxisCode::ABS_HAT0Y => {
+                let last = std::mem::replace(&mut self.hat.1, value.signum());
+                self.on_hat(
+                    last,
+                    value.signum(),
+                    GamepadButton::DPadUp,
+                    GamepadButton::DPadDown,
+                    events,
+                );
+                return;
+            }
+            _ => return,
+        };
+
+        events.push(GamepadEvent::AxisMoved(self.id, axis, value));
+        if !self.has_trigger_buttons {
+            match axis {
+                GamepadAxis::LeftTrigger => {
+                    self.on_trigger(0, value, GamepadButton::LeftTrigger, events)
+                }
+                GamepadAxis::RightTrigger => {
+                    self.on_trigger(1, value, GamepadButton::RightTrigger, events)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_hat(
+        &self,
+        last: i32,
+        current: i32,
+        negative: GamepadButton,
+        positive: GamepadButton,
+        events: &mut Vec<GamepadEvent>,
+    ) {
+        if last == current {
+            return;
+        }
+        let button_for = |direction: i32| if direction < 0 { negative } else { positive };
+        if last != 0 {
+            events.push(GamepadEvent::ButtonReleased(self.id, button_for(last)));
+        }
+        if current != 0 {
+            events.push(GamepadEvent::ButtonPressed(self.id, button_for(current)));
+        }
+    }
+
+    fn on_trigger(
+        &mut self,
+        index: usize,
+        value: f32,
+        button: GamepadButton,
+        events: &mut Vec<GamepadEvent>,
+    ) {
+        let is_down = value > TRIGGER_BUTTON_THRESHOLD;
+        if self.trigger_buttons_down[index] == is_down {
+            return;
+        }
+        self.trigger_buttons_down[index] = is_down;
+        if is_down {
+            events.push(GamepadEvent::ButtonPressed(self.id, button));
+        } else {
+            events.push(GamepadEvent::ButtonReleased(self.id, button));
+        }
+    }
+}
+
+/// Reads gamepads from the evdev nodes in `/dev/input`, which needs read access to them
+/// (usually the `input` group). New nodes are picked up by rescanning the directory.
+pub struct EvdevBackend {
+    directory: PathBuf,
+    gamepads: Vec<EvdevGamepad>,
+    /// Nodes that aren't gamepads or can't be opened, skipped until they disappear.
+    ignored: HashSet<PathBuf>,
+    next_id: u32,
+    scan_interval: Duration,
+    last_scan: Option<Instant>,
+}
+
+impl Default for EvdevBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvdevBackend {
+    pub fn new() -> Self {
+        Self {
+            directory: PathBuf::from(INPUT_DIRECTORY),
+            gamepads: Vec::new(),
+            ignored: HashSet::new(),
+            next_id: 0,
+            scan_interval: Duration::from_secs(1),
+            last_scan: None,
+        }
+    }
+
+    pub fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        if self
+            .last_scan
+            .is_none_or(|last_scan| last_scan.elapsed() >= self.scan_interval)
+        {
+            self.scan(events);
+            self.last_scan = Some(Instant::now());
+        }
+
+        self.gamepads
+            .retain_mut(|gamepad| match gamepad.read_events(events) {
+                Ok(()) => true,
+                Err(e) => {
+                    info_core!("gamepad {} disconnected: {}", gamepad.get_name(), e);
+                    events.push(GamepadEvent::Disconnected(gamepad.id));
+                    false
+                }
+            });
+    }
+
+    fn scan(&mut self, events: &mut Vec<GamepadEvent>) {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("event"))
+            })
+            .collect();
+        paths.sort();
+
+        self.ignored.retain(|path| paths.contains(path));
+        for path in paths {
+            if self.ignored.contains(&path) || self.gamepads.iter().any(|g| g.path == path) {
+                continue;
+            }
+
+            match EvdevGamepad::open(&path, GamepadId(self.next_id)) {
+                Some(gamepad) => {
+                    self.next_id += 1;
+                    info_core!("gamepad {} connected", gamepad.get_name());
+                    events.push(GamepadEvent::Connected(gamepad.id, gamepad.get_name()));
+                    self.gamepads.push(gamepad);
+                }
+                None => {
+                    self.ignored.insert(path);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use evdev::{
+        AbsInfo, AbsoluteAxisEvent, AttributeSet, KeyEvent, UinputAbsSetup, uinput::VirtualDevice,
+    };
+
+    use super::*;
+
+    /// Everything polled until an event matching `found` arrived or two seconds passed.
+    fn poll_until(
+        backend: &mut EvdevBackend,
+        found: impl Fn(&GamepadEvent) -> bool,
+    ) -> Vec<GamepadEvent> {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut events = Vec::new();
+        while Instant::now() < deadline && !events.iter().any(&found) {
+            backend.poll(&mut events);
+            thread::sleep(Duration::from_millis(10));
+        }
+        events
+    }
+
+    /// Needs write access to `/dev/uinput` and read access to the created node, so it only runs
+    /// when asked for with `cargo test -- --ignored` and fails if either is missing.
+    #[test]
+    #[ignore = "needs /dev/uinput"]
+    fn reads_virtual_gamepad() -> io::Result<()> {
+        let builder = VirtualDevice::builder()?;
+        let mut keys = AttributeSet::<KeyCode>::new();
+        keys.insert(KeyCode::BTN_SOUTH);
+        keys.insert(KeyCode::BTN_EAST);
+        let stick = AbsInfo::new(0, -32768, 32767, 16, 128, 0);
+        let mut device = builder
+            .name("strife virtual gamepad")
+            .with_keys(&keys)?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_X, stick))?
+            .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Y, stick))?
+            .build()?;
+
+        let mut backend = EvdevBackend::new();
+        backend.scan_interval = Duration::ZERO;
+        let events = poll_until(
+            &mut backend,
+            |event| matches!(event, GamepadEvent::Connected(_, name) if name == "strife virtual gamepad"),
+        );
+        let Some(&GamepadEvent::Connected(id, _)) = events.last() else {
+            panic!("the virtual gamepad node can't be read");
+        };
+
+        device.emit(&[
+            *KeyEvent::new(KeyCode::BTN_SOUTH, 1),
+            *AbsoluteAxisEvent::new(AbsoluteAxisCode::ABS_Y, -32768),
+        ])?;
+        let pressed = GamepadEvent::ButtonPressed(id, GamepadButton::South);
+        let events = poll_until(&mut backend, |event| *event == pressed);
+        assert!(events.contains(&pressed));
+        assert!(events.contains(&GamepadEvent::AxisMoved(id, GamepadAxis::LeftStickY, 1.0)));
+
+        device.emit(&[*KeyEvent::new(KeyCode::BTN_SOUTH, 0)])?;
+        let released = GamepadEvent::ButtonReleased(id, GamepadButton::South);
+        assert!(poll_until(&mut backend, |event| *event == released).contains(&released));
+
+        drop(device);
+        let disconnected = GamepadEvent::Disconnected(id);
+        assert!(poll_until(&mut backend, |event| *event == disconnected).contains(&disconnected));
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_os = "linux")]
+use super::evdev_backend::EvdevBackend;
+
+/// Gamepad buttons, named after their position on an Xbox style layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    /// Bottom face button (A / Cross).
+    South,
+    /// Right face button (B / Circle).
+    East,
+    /// Left face button (X / Square).
+    West,
+    /// Top face button (Y / Triangle).
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog gamepad inputs. Sticks report `-1.0..=1.0` with up and right positive,
+/// triggers report `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Identifies a connected gamepad, ids are not reused within a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GamepadId(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    Connected(GamepadId, String),
+    Disconnected(GamepadId),
+    ButtonPressed(GamepadId, GamepadButton),
+    ButtonReleased(GamepadId, GamepadButton),
+    AxisMoved(GamepadId, GamepadAxis, f32),
+}
+
+/// Polled state of one connected gamepad.
+#[derive(Debug, Clone, Default)]
+pub struct GamepadState {
+    pub(super) name: String,
+    pub(super) buttons_down: HashSet<GamepadButton>,
+    pub(super) axes: HashMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+    /// After the deadzone, `0.0` until the axis moved.
+    pub fn get_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+/// Values closer to the center than `deadzone` become `0.0`, the rest is rescaled
+/// so the output still starts at `0.0` right outside the deadzone and reaches `±1.0`.
+pub fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        return 0.0;
+    }
+    (value.signum() * (value.abs() - deadzone) / (1.0 - deadzone)).clamp(-1.0, 1.0)
+}
+
+/// Finds connected gamepads and turns their input into [`GamepadEvent`]s.
+/// On Linux gamepads are read through evdev, elsewhere no gamepads are reported yet.
+pub struct Gamepads {
+    #[cfg(target_os = "linux")]
+    backend: EvdevBackend,
+    deadzone: f32,
+    axis_values: HashMap<(GamepadId, GamepadAxis), f32>,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gamepads {
+    pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "linux")]
+            backend: EvdevBackend::new(),
+            deadzone: Self::DEFAULT_DEADZONE,
+            axis_values: HashMap::new(),
+        }
+    }
+
+    pub fn get_deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    /// Deadzone applied to every axis, in `0.0..1.0`.
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+    }
+
+    /// Reads everything that happened since the last poll. Axis events are only
+    /// returned when the value after the deadzone changed.
+    pub fn poll(&mut self) -> Vec<GamepadEvent> {
+        let mut raw_events = Vec::new();
+        #[cfg(target_os = "linux")]
+        self.backend.poll(&mut raw_events);
+
+        let mut events = Vec::with_capacity(raw_events.len());
+        for event in raw_events {
+            match event {
+                GamepadEvent::AxisMoved(id, axis, value) => {
+                    let value = apply_deadzone(value, self.deadzone);
+                    let last_value = self.axis_values.insert((id, axis), value);
+                    if last_value.unwrap_or(0.0) != value {
+                        events.push(GamepadEvent::AxisMoved(id, axis, value));
+                    }
+                }
+                GamepadEvent::Disconnected(id) => {
+                    self.axis_values.retain(|(axis_id, _), _| *axis_id != id);
+                    events.push(event);
+                }
+                _ => events.push(event),
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_zeroes_small_values() {
+        assert_eq!(apply_deadzone(0.1, 0.15), 0.0);
+        assert_eq!(apply_deadzone(-0.15, 0.15), 0.0);
+    }
+
+    #[test]
+    fn deadzone_rescales_the_rest() {
+        assert_eq!(apply_deadzone(1.0, 0.2), 1.0);
+        assert_eq!(apply_deadzone(-1.0, 0.2), -1.0);
+        assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+    }
+}
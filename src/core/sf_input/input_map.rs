@@ -0,0 +1,529 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::MouseButton,
+    keyboard::{KeyCode, ModifiersState},
+};
+
+use crate::core::sf_error::{SfError, SfResult};
+
+use super::{GamepadAxis, GamepadButton, Input};
+
+/// A single key, mouse button or gamepad button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputSource {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl InputSource {
+    pub fn is_down(&self, input: &Input) -> bool {
+        match *self {
+            InputSource::Key(keycode) => input.is_key_down(keycode),
+            InputSource::Mouse(button) => input.is_mouse_button_down(button),
+            InputSource::Gamepad(button) => input.is_gamepad_button_down(button),
+        }
+    }
+
+    pub fn is_just_pressed(&self, input: &Input) -> bool {
+        match *self {
+            InputSource::Key(keycode) => input.is_key_just_pressed(keycode),
+            InputSource::Mouse(button) => input.is_mouse_button_just_pressed(button),
+            InputSource::Gamepad(button) => input.is_gamepad_button_just_pressed(button),
+        }
+    }
+
+    pub fn is_just_released(&self, input: &Input) -> bool {
+        match *self {
+            InputSource::Key(keycode) => input.is_key_just_released(keycode),
+            InputSource::Mouse(button) => input.is_mouse_button_just_released(button),
+            InputSource::Gamepad(button) => input.is_gamepad_button_just_released(button),
+        }
+    }
+
+    /// `1.0` while held, `0.0` otherwise.
+    fn get_value(&self, input: &Input) -> f32 {
+        if self.is_down(input) { 1.0 } else { 0.0 }
+    }
+}
+
+impl From<KeyCode> for InputSource {
+    fn from(value: KeyCode) -> Self {
+        InputSource::Key(value)
+    }
+}
+
+impl From<MouseButton> for InputSource {
+    fn from(value: MouseButton) -> Self {
+        InputSource::Mouse(value)
+    }
+}
+
+impl From<GamepadButton> for InputSource {
+    fn from(value: GamepadButton) -> Self {
+        InputSource::Gamepad(value)
+    }
+}
+
+/// Modifier keys that have to be held for a [`Binding`]. Either the left or the right key counts.
+/// Other modifiers being held doesn't prevent the binding from triggering, unless the [`InputMap`]
+/// has a binding for the same source that needs them, e.g. Ctrl + S hides S while Ctrl is held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub super_key: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        control: false,
+        alt: false,
+        super_key: false,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Whether every modifier of `other` is part of these too.
+    pub fn contains(&self, other: Modifiers) -> bool {
+        (self.shift || !other.shift)
+            && (self.control || !other.control)
+            && (self.alt || !other.alt)
+            && (self.super_key || !other.super_key)
+    }
+
+    pub fn is_held(&self, input: &Input) -> bool {
+        let held = input.get_modifiers();
+        (!self.shift || held.shift_key())
+            && (!self.control || held.control_key())
+            && (!self.alt || held.alt_key())
+            && (!self.super_key || held.super_key())
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(value: ModifiersState) -> Self {
+        Self {
+            shift: value.shift_key(),
+            control: value.control_key(),
+            alt: value.alt_key(),
+            super_key: value.super_key(),
+        }
+    }
+}
+
+/// Triggers an action, e.g. `Space` for "jump" or `Ctrl + S` for "save".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Binding {
+    pub source: InputSource,
+    #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    pub fn new(source: impl Into<InputSource>) -> Self {
+        Self {
+            source: source.into(),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub fn is_down(&self, input: &Input) -> bool {
+        self.source.is_down(input) && self.modifiers.is_held(input)
+    }
+
+    pub fn is_just_pressed(&self, input: &Input) -> bool {
+        self.source.is_just_pressed(input) && self.modifiers.is_held(input)
+    }
+
+    /// Letting go of a modifier first doesn't count as a release, only the source does.
+    pub fn is_just_released(&self, input: &Input) -> bool {
+        self.source.is_just_released(input)
+    }
+}
+
+impl<T: Into<InputSource>> From<T> for Binding {
+    fn from(value: T) -> Self {
+        Binding::new(value)
+    }
+}
+
+/// Produces a value in `-1.0..=1.0` for a named axis, e.g. "move_x".
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// `-1.0` while `negative` is held, `1.0` while `positive` is held, `0.0` for both or neither.
+    Buttons {
+        negative: InputSource,
+        positive: InputSource,
+    },
+    Gamepad {
+        axis: GamepadAxis,
+        #[serde(default)]
+        inverted: bool,
+    },
+}
+
+impl AxisBinding {
+    pub fn get_value(&self, input: &Input) -> f32 {
+        match *self {
+            AxisBinding::Buttons { negative, positive } => {
+                positive.get_value(input) - negative.get_value(input)
+            }
+            AxisBinding::Gamepad { axis, inverted } => {
+                let value = input.get_gamepad_axis(axis);
+                if inverted { -value } else { value }
+            }
+        }
+    }
+}
+
+/// Produces a 2D direction with a length of at most `1.0`, e.g. "move" from WASD or a stick.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Axis2dBinding {
+    /// Composite of four buttons, diagonals are normalized so they aren't faster.
+    Buttons {
+        up: InputSource,
+        down: InputSource,
+        left: InputSource,
+        right: InputSource,
+    },
+    Gamepad {
+        x: GamepadAxis,
+        y: GamepadAxis,
+    },
+}
+
+impl Axis2dBinding {
+    /// The WASD keys.
+    pub const WASD: Axis2dBinding = Axis2dBinding::Buttons {
+        up: InputSource::Key(KeyCode::KeyW),
+        down: InputSource::Key(KeyCode::KeyS),
+        left: InputSource::Key(KeyCode::KeyA),
+        right: InputSource::Key(KeyCode::KeyD),
+    };
+
+    /// The arrow keys.
+    pub const ARROWS: Axis2dBinding = Axis2dBinding::Buttons {
+        up: InputSource::Key(KeyCode::ArrowUp),
+        down: InputSource::Key(KeyCode::ArrowDown),
+        left: InputSource::Key(KeyCode::ArrowLeft),
+        right: InputSource::Key(KeyCode::ArrowRight),
+    };
+
+    pub fn get_value(&self, input: &Input) -> Vec2 {
+        let value = match *self {
+            Axis2dBinding::Buttons {
+                up,
+                down,
+                left,
+                right,
+            } => Vec2::new(
+                right.get_value(input) - left.get_value(input),
+                up.get_value(input) - down.get_value(input),
+            ),
+            Axis2dBinding::Gamepad { x, y } => {
+                Vec2::new(input.get_gamepad_axis(x), input.get_gamepad_axis(y))
+            }
+        };
+        value.clamp_length_max(1.0)
+    }
+}
+
+/// Named actions and axes with their bindings, saved to and loaded from a RON file so players can
+/// rebind them. When several bindings of an axis are active the one with the largest value wins.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    pub actions: BTreeMap<String, Vec<Binding>>,
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+    pub axes_2d: BTreeMap<String, Vec<Axis2dBinding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind_action(&mut self, action: &str, binding: impl Into<Binding>) -> &mut Self {
+        self.actions
+            .entry(action.to_string())
+            .or_default()
+            .push(binding.into());
+        self
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) -> &mut Self {
+        self.axes.entry(axis.to_string()).or_default().push(binding);
+        self
+    }
+
+    pub fn bind_axis_2d(&mut self, axis: &str, binding: Axis2dBinding) -> &mut Self {
+        self.axes_2d
+            .entry(axis.to_string())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    /// Replaces `old` with `new` in the bindings of `action`, or adds `new` if `old` isn't bound.
+    pub fn rebind_action(&mut self, action: &str, old: &Binding, new: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        match bindings.iter_mut().find(|binding| *binding == old) {
+            Some(binding) => *binding = new,
+            None => bindings.push(new),
+        }
+    }
+
+    pub fn unbind_action(&mut self, action: &str, binding: &Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|b| b != binding);
+        }
+    }
+
+    pub fn get_action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds the actions and axes of `defaults` this map doesn't know yet, e.g. ones added to the
+    /// game after the player saved their bindings. Existing bindings are kept as they are.
+    pub fn extend_missing(&mut self, defaults: &InputMap) {
+        for (action, bindings) in &defaults.actions {
+            self.actions
+                .entry(action.clone())
+                .or_insert_with(|| bindings.clone());
+        }
+        for (axis, bindings) in &defaults.axes {
+            self.axes
+                .entry(axis.clone())
+                .or_insert_with(|| bindings.clone());
+        }
+        for (axis, bindings) in &defaults.axes_2d {
+            self.axes_2d
+                .entry(axis.clone())
+                .or_insert_with(|| bindings.clone());
+        }
+    }
+
+    pub fn is_action_down(&self, input: &Input, action: &str) -> bool {
+        self.get_action_bindings(action)
+            .iter()
+            .any(|binding| binding.is_down(input) && !self.is_overridden(input, binding))
+    }
+
+    pub fn is_action_just_pressed(&self, input: &Input, action: &str) -> bool {
+        self.get_action_bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_pressed(input) && !self.is_overridden(input, binding))
+    }
+
+    /// Whether a binding of any action has the same source and more modifiers, all of them held.
+    /// The binding with the most modifiers wins, so Ctrl + S doesn't also trigger S.
+    fn is_overridden(&self, input: &Input, binding: &Binding) -> bool {
+        self.actions.values().flatten().any(|other| {
+            other.source == binding.source
+                && other.modifiers != binding.modifiers
+                && other.modifiers.contains(binding.modifiers)
+                && other.modifiers.is_held(input)
+        })
+    }
+
+    pub fn is_action_just_released(&self, input: &Input, action: &str) -> bool {
+        self.get_action_bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_released(input))
+    }
+
+    /// `0.0` for unknown axes.
+    pub fn get_axis(&self, input: &Input, axis: &str) -> f32 {
+        self.axes
+            .get(axis)
+            .into_iter()
+            .flatten()
+            .map(|binding| binding.get_value(input))
+            .fold(0.0, |value: f32, binding_value| {
+                if binding_value.abs() > value.abs() {
+                    binding_value
+                } else {
+                    value
+                }
+            })
+    }
+
+    /// `Vec2::ZERO` for unknown axes.
+    pub fn get_axis_2d(&self, input: &Input, axis: &str) -> Vec2 {
+        self.axes_2d
+            .get(axis)
+            .into_iter()
+            .flatten()
+            .map(|binding| binding.get_value(input))
+            .fold(Vec2::ZERO, |value, binding_value| {
+                if binding_value.length_squared() > value.length_squared() {
+                    binding_value
+                } else {
+                    value
+                }
+            })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> SfResult<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| SfError::InputMapReadFailed(path.to_path_buf(), e))?;
+        ron::from_str(&source).map_err(|e| SfError::InputMapParseFailed(path.to_path_buf(), e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> SfResult<()> {
+        let path = path.as_ref();
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(SfError::InputMapSerializeFailed)?;
+        fs::write(path, source).map_err(|e| SfError::InputMapWriteFailed(path.to_path_buf(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sf_input::{GamepadEvent, GamepadId};
+
+    fn ctrl() -> Modifiers {
+        Modifiers {
+            control: true,
+            ..Modifiers::NONE
+        }
+    }
+
+    #[test]
+    fn saved_maps_load_back_the_same() {
+        let mut input_map = InputMap::new();
+        input_map
+            .bind_action("save", Binding::new(KeyCode::KeyS).with_modifiers(ctrl()))
+            .bind_action("fire", MouseButton::Left)
+            .bind_axis(
+                "throttle",
+                AxisBinding::Gamepad {
+                    axis: GamepadAxis::RightTrigger,
+                    inverted: true,
+                },
+            )
+            .bind_axis_2d("move", Axis2dBinding::WASD);
+
+        let path = std::env::temp_dir().join(format!("strife-input-{}.ron", std::process::id()));
+        input_map.save(&path).unwrap();
+        let loaded = InputMap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), input_map);
+    }
+
+    #[test]
+    fn extend_missing_keeps_existing_bindings() {
+        let mut defaults = InputMap::new();
+        defaults
+            .bind_action("jump", KeyCode::Space)
+            .bind_action("crouch", KeyCode::KeyC)
+            .bind_axis_2d("move", Axis2dBinding::WASD);
+        let mut player = InputMap::new();
+        player.bind_action("jump", KeyCode::KeyJ);
+
+        player.extend_missing(&defaults);
+
+        assert_eq!(
+            player.get_action_bindings("jump"),
+            [Binding::new(KeyCode::KeyJ)]
+        );
+        assert_eq!(
+            player.get_action_bindings("crouch"),
+            [Binding::new(KeyCode::KeyC)]
+        );
+        assert_eq!(player.axes_2d["move"], [Axis2dBinding::WASD]);
+    }
+
+    #[test]
+    fn diagonals_are_not_faster() {
+        let mut input_map = InputMap::new();
+        input_map.bind_axis_2d("move", Axis2dBinding::WASD);
+        let mut input = Input::new();
+        input.on_key_pressed(KeyCode::KeyW);
+        input.on_key_pressed(KeyCode::KeyD);
+
+        let value = input_map.get_axis_2d(&input, "move");
+        assert!((value.length() - 1.0).abs() < 1e-6, "{}", value);
+        assert!((value.x - value.y).abs() < 1e-6, "{}", value);
+        assert_eq!(input_map.get_axis_2d(&input, "unknown"), Vec2::ZERO);
+    }
+
+    #[test]
+    fn axis_takes_the_largest_binding_value() {
+        let mut input_map = InputMap::new();
+        input_map
+            .bind_axis(
+                "steer",
+                AxisBinding::Buttons {
+                    negative: InputSource::Key(KeyCode::KeyA),
+                    positive: InputSource::Key(KeyCode::KeyD),
+                },
+            )
+            .bind_axis(
+                "steer",
+                AxisBinding::Gamepad {
+                    axis: GamepadAxis::LeftStickX,
+                    inverted: false,
+                },
+            );
+        let mut input = Input::new();
+        input.on_gamepad_event(&GamepadEvent::AxisMoved(
+            GamepadId(0),
+            GamepadAxis::LeftStickX,
+            0.4,
+        ));
+        assert_eq!(input_map.get_axis(&input, "steer"), 0.4);
+
+        input.on_key_pressed(KeyCode::KeyA);
+        assert_eq!(input_map.get_axis(&input, "steer"), -1.0);
+        assert_eq!(input_map.get_axis(&input, "unknown"), 0.0);
+    }
+
+    #[test]
+    fn bindings_with_more_modifiers_win() {
+        let mut input_map = InputMap::new();
+        input_map
+            .bind_action("save", Binding::new(KeyCode::KeyS).with_modifiers(ctrl()))
+            .bind_action("back", KeyCode::KeyS)
+            .bind_action("fire", KeyCode::KeyF);
+        let mut input = Input::new();
+
+        input.on_key_pressed(KeyCode::KeyS);
+        assert!(input_map.is_action_just_pressed(&input, "back"));
+        assert!(!input_map.is_action_down(&input, "save"));
+        input.on_key_released(KeyCode::KeyS);
+        input.end_frame();
+
+        input.on_modifiers_changed(ModifiersState::CONTROL | ModifiersState::SHIFT);
+        input.on_key_pressed(KeyCode::KeyS);
+        input.on_key_pressed(KeyCode::KeyF);
+        assert!(input_map.is_action_just_pressed(&input, "save"));
+        assert!(input_map.is_action_down(&input, "save"));
+        assert!(!input_map.is_action_just_pressed(&input, "back"));
+        assert!(!input_map.is_action_down(&input, "back"));
+        // Unrelated bindings still ignore extra modifiers.
+        assert!(input_map.is_action_down(&input, "fire"));
+
+        // Letting go of Ctrl first still releases the binding with the source.
+        input.on_modifiers_changed(ModifiersState::empty());
+        input.on_key_released(KeyCode::KeyS);
+        assert!(input_map.is_action_just_released(&input, "save"));
+    }
+}
@@ -0,0 +1,508 @@
+#[cfg(target_os = "linux")]
+mod evdev_backend;
+pub mod gamepad;
+pub use gamepad::*;
+pub mod input_map;
+pub use input_map::*;
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashSet},
+};
+
+use glam::Vec2;
+use winit::{
+    event::MouseButton,
+    keyboard::{KeyCode, ModifiersState},
+    window::WindowId,
+};
+
+use super::sf_events::{
+    CursorLeftEvent, Eventable, GamepadAxisMovedEvent, GamepadButtonPressedEvent,
+    GamepadButtonReleasedEvent, GamepadConnectedEvent, GamepadDisconnectedEvent, KeyPressedEvent,
+    KeyReleasedEvent, ModifiersChangedEvent, MouseButtonPressedEvent, MouseButtonReleasedEvent,
+    MouseMoveEvent, MouseScrolledEvent, ScrollUnit, WindowFocusEvent,
+};
+
+/// Pixel scroll deltas (touchpads) are converted to lines with this, so both add up in one value.
+pub const PIXELS_PER_SCROLL_LINE: f64 = 20.0;
+
+/// Keyboard, mouse and gamepad state, kept up to date by the window manager and queried from layers
+/// during `on_update`, either directly or through the named actions and axes of its [`InputMap`].
+/// "Just pressed/released" and the deltas cover everything since the previous frame and are reset
+/// when the frame ends.
+#[derive(Debug, Default)]
+pub struct Input {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    modifiers: ModifiersState,
+    gamepads: BTreeMap<GamepadId, GamepadState>,
+    /// Buttons no gamepad held before and one does now, and the other way around.
+    gamepad_buttons_pressed: HashSet<GamepadButton>,
+    gamepad_buttons_released: HashSet<GamepadButton>,
+    /// Everything pressed this frame in order, for rebinding UIs.
+    sources_pressed: Vec<InputSource>,
+    input_map: InputMap,
+    cursor_window: Option<WindowId>,
+    mouse_position: Option<(f64, f64)>,
+    mouse_delta: (f64, f64),
+    scroll_delta: (f64, f64),
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_key_down(&self, keycode: KeyCode) -> bool {
+        self.keys_down.contains(&keycode)
+    }
+    pub fn is_key_just_pressed(&self, keycode: KeyCode) -> bool {
+        self.keys_pressed.contains(&keycode)
+    }
+    pub fn is_key_just_released(&self, keycode: KeyCode) -> bool {
+        self.keys_released.contains(&keycode)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+    pub fn is_mouse_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+    pub fn is_mouse_button_just_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    pub fn get_modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    pub fn get_gamepad(&self, id: GamepadId) -> Option<&GamepadState> {
+        self.gamepads.get(&id)
+    }
+    /// Connected gamepads, oldest first.
+    pub fn get_gamepad_ids(&self) -> impl Iterator<Item = GamepadId> + '_ {
+        self.gamepads.keys().copied()
+    }
+
+    /// The queries below combine all connected gamepads, so any of them can be used to play.
+    pub fn is_gamepad_button_down(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|gamepad| gamepad.is_button_down(button))
+    }
+    pub fn is_gamepad_button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons_pressed.contains(&button)
+    }
+    pub fn is_gamepad_button_just_released(&self, button: GamepadButton) -> bool {
+        self.gamepad_buttons_released.contains(&button)
+    }
+    /// The value furthest from `0.0` across all gamepads.
+    pub fn get_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .values()
+            .map(|gamepad| gamepad.get_axis(axis))
+            .fold(0.0, |value: f32, gamepad_value| {
+                if gamepad_value.abs() > value.abs() {
+                    gamepad_value
+                } else {
+                    value
+                }
+            })
+    }
+
+    /// The first key or button pressed this frame, used to let players pick a new binding.
+    pub fn get_first_just_pressed(&self) -> Option<InputSource> {
+        self.sources_pressed.first().copied()
+    }
+
+    pub fn get_input_map(&self) -> &InputMap {
+        &self.input_map
+    }
+    pub fn get_input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input_map
+    }
+    pub fn set_input_map(&mut self, input_map: InputMap) {
+        self.input_map = input_map;
+    }
+
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.input_map.is_action_down(self, action)
+    }
+    pub fn is_action_just_pressed(&self, action: &str) -> bool {
+        self.input_map.is_action_just_pressed(self, action)
+    }
+    pub fn is_action_just_released(&self, action: &str) -> bool {
+        self.input_map.is_action_just_released(self, action)
+    }
+    pub fn get_axis(&self, axis: &str) -> f32 {
+        self.input_map.get_axis(self, axis)
+    }
+    pub fn get_axis_2d(&self, axis: &str) -> Vec2 {
+        self.input_map.get_axis_2d(self, axis)
+    }
+
+    /// Cursor position inside [`Input::get_cursor_window`], `None` while the cursor is outside every window.
+    pub fn get_mouse_position(&self) -> Option<(f64, f64)> {
+        self.mouse_position
+    }
+    pub fn get_cursor_window(&self) -> Option<WindowId> {
+        self.cursor_window
+    }
+    /// Cursor movement since the previous frame, in physical pixels.
+    pub fn get_mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+    /// Scrolling since the previous frame, in lines.
+    pub fn get_scroll_delta(&self) -> (f64, f64) {
+        self.scroll_delta
+    }
+
+    /// Updates the state from an engine event, other events are ignored. The window manager feeds
+    /// every event through here after dispatching it, so replayed events update the state the same way.
+    /// Presses, scrolling and mouse movement a listener or layer handled, e.g. a click the GUI captured,
+    /// are left out; releases always count so nothing stays held.
+    pub fn on_event(&mut self, event: &dyn Eventable) {
+        let is_handled = event.is_handled();
+        let event = event as &dyn Any;
+        if let Some(e) = event.downcast_ref::<KeyPressedEvent>() {
+            if !is_handled {
+                self.on_key_pressed(e.keycode);
+            }
+        } else if let Some(e) = event.downcast_ref::<KeyReleasedEvent>() {
+            self.on_key_released(e.keycode);
+        } else if let Some(e) = event.downcast_ref::<MouseButtonPressedEvent>() {
+            if !is_handled {
+                self.on_mouse_button_pressed(e.button);
+            }
+        } else if let Some(e) = event.downcast_ref::<MouseButtonReleasedEvent>() {
+            self.on_mouse_button_released(e.button);
+        } else if let Some(e) = event.downcast_ref::<MouseMoveEvent>() {
+            if is_handled {
+                self.set_cursor(e.window_id, e.x, e.y);
+            } else {
+                self.on_mouse_moved(e.window_id, e.x, e.y);
+            }
+        } else if let Some(e) = event.downcast_ref::<CursorLeftEvent>() {
+            self.on_cursor_left(e.window_id);
+        } else if let Some(e) = event.downcast_ref::<MouseScrolledEvent>() {
+            if !is_handled {
+                self.on_scrolled(e.x_offset, e.y_offset, e.unit);
+            }
+        } else if let Some(e) = event.downcast_ref::<ModifiersChangedEvent>() {
+            self.on_modifiers_changed(e.modifiers);
+        } else if let Some(e) = event.downcast_ref::<WindowFocusEvent>() {
+            if !e.is_focused {
+                self.on_focus_lost();
+            }
+        } else if let Some(e) = event.downcast_ref::<GamepadConnectedEvent>() {
+            self.on_gamepad_event(&GamepadEvent::Connected(
+                e.gamepad_id,
+                e.gamepad_name.clone(),
+            ));
+        } else if let Some(e) = event.downcast_ref::<GamepadDisconnectedEvent>() {
+            self.on_gamepad_event(&GamepadEvent::Disconnected(e.gamepad_id));
+        } else if let Some(e) = event.downcast_ref::<GamepadButtonPressedEvent>() {
+            self.on_gamepad_event(&GamepadEvent::ButtonPressed(e.gamepad_id, e.button));
+        } else if let Some(e) = event.downcast_ref::<GamepadButtonReleasedEvent>() {
+            self.on_gamepad_event(&GamepadEvent::ButtonReleased(e.gamepad_id, e.button));
+        } else if let Some(e) = event.downcast_ref::<GamepadAxisMovedEvent>() {
+            self.on_gamepad_event(&GamepadEvent::AxisMoved(e.gamepad_id, e.axis, e.value));
+        }
+    }
+
+    pub fn on_key_pressed(&mut self, keycode: KeyCode) {
+        if self.keys_down.insert(keycode) {
+            self.keys_pressed.insert(keycode);
+            self.sources_pressed.push(InputSource::Key(keycode));
+        }
+    }
+    pub fn on_key_released(&mut self, keycode: KeyCode) {
+        if self.keys_down.remove(&keycode) {
+            self.keys_released.insert(keycode);
+        }
+    }
+
+    pub fn on_mouse_button_pressed(&mut self, button: MouseButton) {
+        if self.buttons_down.insert(button) {
+            self.buttons_pressed.insert(button);
+            self.sources_pressed.push(InputSource::Mouse(button));
+        }
+    }
+    pub fn on_mouse_button_released(&mut self, button: MouseButton) {
+        if self.buttons_down.remove(&button) {
+            self.buttons_released.insert(button);
+        }
+    }
+
+    pub fn on_modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// Disconnecting releases the buttons the gamepad held.
+    pub fn on_gamepad_event(&mut self, event: &GamepadEvent) {
+        match event {
+            GamepadEvent::Connected(id, name) => {
+                self.gamepads.entry(*id).or_default().name = name.clone();
+            }
+            GamepadEvent::Disconnected(id) => {
+                if let Some(gamepad) = self.gamepads.remove(id) {
+                    for button in gamepad.buttons_down {
+                        if !self.is_gamepad_button_down(button) {
+                            self.gamepad_buttons_released.insert(button);
+                        }
+                    }
+                }
+            }
+            GamepadEvent::ButtonPressed(id, button) => {
+                let was_down = self.is_gamepad_button_down(*button);
+                self.gamepads
+                    .entry(*id)
+                    .or_default()
+                    .buttons_down
+                    .insert(*button);
+                if !was_down {
+                    self.gamepad_buttons_pressed.insert(*button);
+                    self.sources_pressed.push(InputSource::Gamepad(*button));
+                }
+            }
+            GamepadEvent::ButtonReleased(id, button) => {
+                let Some(gamepad) = self.gamepads.get_mut(id) else {
+                    return;
+                };
+                if gamepad.buttons_down.remove(button) && !self.is_gamepad_button_down(*button) {
+                    self.gamepad_buttons_released.insert(*button);
+                }
+            }
+            GamepadEvent::AxisMoved(id, axis, value) => {
+                self.gamepads
+                    .entry(*id)
+                    .or_default()
+                    .axes
+                    .insert(*axis, *value);
+            }
+        }
+    }
+
+    /// Moving into another window doesn't count as movement.
+    pub fn on_mouse_moved(&mut self, window_id: WindowId, x: f64, y: f64) {
+        if self.cursor_window == Some(window_id)
+            && let Some((last_x, last_y)) = self.mouse_position
+        {
+            self.mouse_delta.0 += x - last_x;
+            self.mouse_delta.1 += y - last_y;
+        }
+        self.set_cursor(window_id, x, y);
+    }
+
+    fn set_cursor(&mut self, window_id: WindowId, x: f64, y: f64) {
+        self.cursor_window = Some(window_id);
+        self.mouse_position = Some((x, y));
+    }
+
+    pub fn on_cursor_left(&mut self, window_id: WindowId) {
+        if self.cursor_window == Some(window_id) {
+            self.cursor_window = None;
+            self.mouse_position = None;
+        }
+    }
+
+    pub fn on_scrolled(&mut self, x_offset: f64, y_offset: f64, unit: ScrollUnit) {
+        let scale = match unit {
+            ScrollUnit::Lines => 1.0,
+            ScrollUnit::Pixels => 1.0 / PIXELS_PER_SCROLL_LINE,
+        };
+        self.scroll_delta.0 += x_offset * scale;
+        self.scroll_delta.1 += y_offset * scale;
+    }
+
+    /// Releases everything that is held, the release events of an unfocused window are never received.
+    pub fn on_focus_lost(&mut self) {
+        self.keys_released.extend(self.keys_down.drain());
+        self.buttons_released.extend(self.buttons_down.drain());
+        self.modifiers = ModifiersState::empty();
+    }
+
+    /// Resets the per frame state, called after the frame's updates ran.
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.gamepad_buttons_pressed.clear();
+        self.gamepad_buttons_released.clear();
+        self.sources_pressed.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll_delta = (0.0, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_repeat_does_not_press_again() {
+        let mut input = Input::new();
+        input.on_key_pressed(KeyCode::KeyA);
+        assert!(input.is_key_just_pressed(KeyCode::KeyA));
+        input.end_frame();
+
+        // Held keys keep sending pressed events.
+        input.on_key_pressed(KeyCode::KeyA);
+        assert!(input.is_key_down(KeyCode::KeyA));
+        assert!(!input.is_key_just_pressed(KeyCode::KeyA));
+        assert_eq!(input.get_first_just_pressed(), None);
+
+        input.on_key_released(KeyCode::KeyA);
+        input.on_key_released(KeyCode::KeyA);
+        assert!(input.is_key_just_released(KeyCode::KeyA));
+        assert!(!input.is_key_down(KeyCode::KeyA));
+    }
+
+    #[test]
+    fn end_frame_resets_the_per_frame_state() {
+        let window = WindowId::from(1);
+        let mut input = Input::new();
+        input.on_key_pressed(KeyCode::KeyA);
+        input.on_key_pressed(KeyCode::KeyB);
+        input.on_key_released(KeyCode::KeyB);
+        input.on_mouse_button_pressed(MouseButton::Left);
+        input.on_gamepad_event(&GamepadEvent::ButtonPressed(
+            GamepadId(0),
+            GamepadButton::South,
+        ));
+        input.on_mouse_moved(window, 10.0, 10.0);
+        input.on_mouse_moved(window, 15.0, 12.0);
+        input.on_scrolled(0.0, 2.0, ScrollUnit::Lines);
+        assert_eq!(input.get_mouse_delta(), (5.0, 2.0));
+        assert_eq!(
+            input.get_first_just_pressed(),
+            Some(InputSource::Key(KeyCode::KeyA))
+        );
+
+        input.end_frame();
+
+        assert!(!input.is_key_just_pressed(KeyCode::KeyA));
+        assert!(!input.is_key_just_released(KeyCode::KeyB));
+        assert!(!input.is_mouse_button_just_pressed(MouseButton::Left));
+        assert!(!input.is_gamepad_button_just_pressed(GamepadButton::South));
+        assert_eq!(input.get_first_just_pressed(), None);
+        assert_eq!(input.get_mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.get_scroll_delta(), (0.0, 0.0));
+        // Held state and the cursor position carry over.
+        assert!(input.is_key_down(KeyCode::KeyA));
+        assert!(input.is_mouse_button_down(MouseButton::Left));
+        assert!(input.is_gamepad_button_down(GamepadButton::South));
+        assert_eq!(input.get_mouse_position(), Some((15.0, 12.0)));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything_held() {
+        let mut input = Input::new();
+        input.on_key_pressed(KeyCode::KeyW);
+        input.on_mouse_button_pressed(MouseButton::Right);
+        input.on_modifiers_changed(ModifiersState::SHIFT);
+        input.end_frame();
+
+        input.on_event(&WindowFocusEvent {
+            name: String::new(),
+            window_id: WindowId::from(1),
+            is_focused: false,
+            is_handled: false,
+        });
+
+        assert!(!input.is_key_down(KeyCode::KeyW));
+        assert!(input.is_key_just_released(KeyCode::KeyW));
+        assert!(!input.is_mouse_button_down(MouseButton::Right));
+        assert!(input.is_mouse_button_just_released(MouseButton::Right));
+        assert_eq!(input.get_modifiers(), ModifiersState::empty());
+    }
+
+    #[test]
+    fn handled_events_press_nothing_but_still_release() {
+        let window = WindowId::from(1);
+        let device_id = winit::event::DeviceId::dummy();
+        let mut input = Input::new();
+        input.on_mouse_button_pressed(MouseButton::Right);
+        input.on_mouse_moved(window, 10.0, 10.0);
+        input.end_frame();
+
+        // A click and scroll the GUI captured.
+        input.on_event(&MouseButtonPressedEvent {
+            name: String::new(),
+            is_handled: true,
+            window_id: window,
+            device_id,
+            button: MouseButton::Left,
+        });
+        input.on_event(&MouseMoveEvent {
+            name: String::new(),
+            x: 20.0,
+            y: 15.0,
+            is_handled: true,
+            window_id: window,
+            device_id,
+        });
+        let mut scrolled = MouseScrolledEvent::from_delta(
+            window,
+            device_id,
+            winit::event::MouseScrollDelta::LineDelta(0.0, 1.0),
+            winit::event::TouchPhase::Moved,
+        );
+        scrolled.is_handled = true;
+        input.on_event(&scrolled);
+        input.on_event(&MouseButtonReleasedEvent {
+            name: String::new(),
+            is_handled: true,
+            window_id: window,
+            device_id,
+            button: MouseButton::Right,
+        });
+
+        assert!(!input.is_mouse_button_down(MouseButton::Left));
+        assert!(!input.is_mouse_button_just_pressed(MouseButton::Left));
+        assert_eq!(input.get_first_just_pressed(), None);
+        assert_eq!(input.get_scroll_delta(), (0.0, 0.0));
+        assert_eq!(input.get_mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.get_mouse_position(), Some((20.0, 15.0)));
+        assert!(input.is_mouse_button_just_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn moving_into_another_window_is_no_movement() {
+        let (first, second) = (WindowId::from(1), WindowId::from(2));
+        let mut input = Input::new();
+        input.on_mouse_moved(first, 100.0, 100.0);
+        input.on_mouse_moved(first, 110.0, 100.0);
+
+        input.on_mouse_moved(second, 5.0, 5.0);
+        assert_eq!(input.get_mouse_delta(), (10.0, 0.0));
+        assert_eq!(input.get_cursor_window(), Some(second));
+
+        // Neither is coming back after the cursor left.
+        input.on_cursor_left(second);
+        assert_eq!(input.get_mouse_position(), None);
+        input.on_mouse_moved(second, 50.0, 50.0);
+        assert_eq!(input.get_mouse_delta(), (10.0, 0.0));
+        assert_eq!(input.get_mouse_position(), Some((50.0, 50.0)));
+    }
+
+    #[test]
+    fn pixel_scrolling_is_converted_to_lines() {
+        let mut input = Input::new();
+        input.on_scrolled(0.0, 1.0, ScrollUnit::Lines);
+        input.on_scrolled(
+            PIXELS_PER_SCROLL_LINE,
+            -PIXELS_PER_SCROLL_LINE * 3.0,
+            ScrollUnit::Pixels,
+        );
+
+        assert_eq!(input.get_scroll_delta(), (1.0, -2.0));
+    }
+}
@@ -1,38 +1,484 @@
-use super::sf_events::Eventable;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
 
+use super::{
+    sf_events::{EventCategoryFlags, Eventable},
+    sf_time::Timestep,
+};
+
+/// Identifies a pushed layer or overlay, e.g. to remove it later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerHandle(u64);
+
+pub enum LayerCommand {
+    PushLayer(LayerHandle, Box<dyn Layer>),
+    PushOverlay(LayerHandle, Box<dyn Layer>),
+    PopLayer,
+    PopOverlay,
+    Remove(LayerHandle),
+    RemoveByName(String),
+}
+
+#[derive(Default)]
+struct LayerCommandQueue {
+    commands: VecDeque<LayerCommand>,
+    next_handle: u64,
+}
+
+/// Queues changes to a [`LayerStack`] from places that can't borrow it, like a layer's `on_update` or `on_event`.
+/// The commands are applied in order by [`LayerStack::apply_commands`].
+#[derive(Clone, Default)]
+pub struct LayerCommands {
+    queue: Rc<RefCell<LayerCommandQueue>>,
+}
+
+impl LayerCommands {
+    pub fn push_layer(&self, layer: Box<dyn Layer>) -> LayerHandle {
+        let handle = self.allocate_handle();
+        self.push(LayerCommand::PushLayer(handle, layer));
+        handle
+    }
+
+    pub fn push_overlay(&self, overlay: Box<dyn Layer>) -> LayerHandle {
+        let handle = self.allocate_handle();
+        self.push(LayerCommand::PushOverlay(handle, overlay));
+        handle
+    }
+
+    pub fn pop_layer(&self) {
+        self.push(LayerCommand::PopLayer);
+    }
+
+    pub fn pop_overlay(&self) {
+        self.push(LayerCommand::PopOverlay);
+    }
+
+    pub fn remove(&self, handle: LayerHandle) {
+        self.push(LayerCommand::Remove(handle));
+    }
+
+    pub fn remove_by_name(&self, name: &str) {
+        self.push(LayerCommand::RemoveByName(name.to_string()));
+    }
+
+    pub fn push(&self, command: LayerCommand) {
+        self.queue.borrow_mut().commands.push_back(command);
+    }
+
+    fn allocate_handle(&self) -> LayerHandle {
+        let mut queue = self.queue.borrow_mut();
+        let handle = LayerHandle(queue.next_handle);
+        queue.next_handle += 1;
+        handle
+    }
+
+    fn take(&self) -> Option<LayerCommand> {
+        self.queue.borrow_mut().commands.pop_front()
+    }
+}
+
+struct LayerEntry {
+    handle: LayerHandle,
+    layer: Box<dyn Layer>,
+}
+
+/// Owns the layers and drives their lifecycle: `on_attach` is called when a layer is pushed
+/// and `on_detach` when it is removed or the stack is dropped.
+///
+/// Layers and overlays live in separate regions, every overlay is above every layer.
+/// Updates run bottom-up and events are dispatched top-down, so overlays see events first.
 pub struct LayerStack {
-    pub layers: Vec<Box<dyn Layer>>,
-    layer_insert_index: usize,
+    layers: Vec<LayerEntry>,
+    overlays: Vec<LayerEntry>,
+    commands: LayerCommands,
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LayerStack {
     pub fn new() -> Self {
         Self {
             layers: Vec::new(),
-            layer_insert_index: 0,
+            overlays: Vec::new(),
+            commands: LayerCommands::default(),
+        }
+    }
+
+    /// Pushes a layer on top of the other layers, below every overlay.
+    pub fn push_layer(&mut self, layer: Box<dyn Layer>) -> LayerHandle {
+        let handle = self.commands.allocate_handle();
+        Self::attach(&mut self.layers, handle, layer);
+        handle
+    }
+
+    /// Pushes an overlay on top of the whole stack.
+    pub fn push_overlay(&mut self, overlay: Box<dyn Layer>) -> LayerHandle {
+        let handle = self.commands.allocate_handle();
+        Self::attach(&mut self.overlays, handle, overlay);
+        handle
+    }
+
+    /// Removes the topmost layer, overlays are left alone.
+    pub fn pop_layer(&mut self) -> Option<Box<dyn Layer>> {
+        let index = self.layers.len().checked_sub(1)?;
+        Some(Self::detach(&mut self.layers, index))
+    }
+
+    /// Removes the topmost overlay.
+    pub fn pop_overlay(&mut self) -> Option<Box<dyn Layer>> {
+        let index = self.overlays.len().checked_sub(1)?;
+        Some(Self::detach(&mut self.overlays, index))
+    }
+
+    /// Removes a layer or overlay, returns `None` if it isn't in the stack anymore.
+    pub fn remove(&mut self, handle: LayerHandle) -> Option<Box<dyn Layer>> {
+        if let Some(index) = self.overlays.iter().position(|e| e.handle == handle) {
+            return Some(Self::detach(&mut self.overlays, index));
+        }
+        let index = self.layers.iter().position(|e| e.handle == handle)?;
+        Some(Self::detach(&mut self.layers, index))
+    }
+
+    /// Removes the topmost layer or overlay with the given name.
+    pub fn remove_by_name(&mut self, name: &str) -> Option<Box<dyn Layer>> {
+        if let Some(index) = Self::rposition_by_name(&mut self.overlays, name) {
+            return Some(Self::detach(&mut self.overlays, index));
+        }
+        let index = Self::rposition_by_name(&mut self.layers, name)?;
+        Some(Self::detach(&mut self.layers, index))
+    }
+
+    pub fn get(&mut self, handle: LayerHandle) -> Option<&mut Box<dyn Layer>> {
+        self.layers
+            .iter_mut()
+            .chain(self.overlays.iter_mut())
+            .find(|e| e.handle == handle)
+            .map(|e| &mut e.layer)
+    }
+
+    /// Finds the topmost layer or overlay with the given name.
+    pub fn get_by_name(&mut self, name: &str) -> Option<&mut Box<dyn Layer>> {
+        let index = self
+            .iter_top_down_mut()
+            .position(|layer| layer.get_name() == name)?;
+        self.iter_top_down_mut().nth(index)
+    }
+
+    pub fn contains(&self, handle: LayerHandle) -> bool {
+        self.layers
+            .iter()
+            .chain(self.overlays.iter())
+            .any(|e| e.handle == handle)
+    }
+
+    /// Layers first, then overlays, in the order updates run.
+    pub fn iter_bottom_up_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Layer>> {
+        self.layers
+            .iter_mut()
+            .chain(self.overlays.iter_mut())
+            .map(|e| &mut e.layer)
+    }
+
+    /// Overlays first, then layers, in the order events are dispatched.
+    pub fn iter_top_down_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Layer>> {
+        self.overlays
+            .iter_mut()
+            .rev()
+            .chain(self.layers.iter_mut().rev())
+            .map(|e| &mut e.layer)
+    }
+
+    pub fn get_layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn get_overlay_count(&self) -> usize {
+        self.overlays.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len() + self.overlays.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty() && self.overlays.is_empty()
+    }
+
+    /// Returns a queue for changing this stack later, see [`LayerCommands`].
+    pub fn get_commands(&self) -> LayerCommands {
+        self.commands.clone()
+    }
+
+    /// Applies the queued [`LayerCommands`], including ones queued while applying.
+    pub fn apply_commands(&mut self) {
+        while let Some(command) = self.commands.take() {
+            match command {
+                LayerCommand::PushLayer(handle, layer) => {
+                    Self::attach(&mut self.layers, handle, layer)
+                }
+                LayerCommand::PushOverlay(handle, overlay) => {
+                    Self::attach(&mut self.overlays, handle, overlay)
+                }
+                LayerCommand::PopLayer => {
+                    self.pop_layer();
+                }
+                LayerCommand::PopOverlay => {
+                    self.pop_overlay();
+                }
+                LayerCommand::Remove(handle) => {
+                    self.remove(handle);
+                }
+                LayerCommand::RemoveByName(name) => {
+                    self.remove_by_name(&name);
+                }
+            }
         }
     }
 
-    pub fn push_layer(&mut self, layer: Box<dyn Layer>) {
-        self.layers.insert(self.layer_insert_index, layer);
-        self.layer_insert_index += 1;
+    fn attach(region: &mut Vec<LayerEntry>, handle: LayerHandle, layer: Box<dyn Layer>) {
+        region.push(LayerEntry { handle, layer });
+        if let Some(entry) = region.last_mut() {
+            entry.layer.on_attach();
+        }
     }
-    pub fn push_overlay(&mut self, overlay: Box<dyn Layer>) {
-        self.layers.push(overlay);
+
+    fn detach(region: &mut Vec<LayerEntry>, index: usize) -> Box<dyn Layer> {
+        let mut entry = region.remove(index);
+        entry.layer.on_detach();
+        entry.layer
     }
 
-    pub fn pop_layer(&mut self) {
-        if self.layer_insert_index > 0 {
-            self.layers.remove(self.layer_insert_index);
-            self.layer_insert_index -= 1;
+    fn rposition_by_name(region: &mut [LayerEntry], name: &str) -> Option<usize> {
+        region.iter_mut().rposition(|e| e.layer.get_name() == name)
+    }
+}
+
+impl Drop for LayerStack {
+    fn drop(&mut self) {
+        for layer in self.iter_top_down_mut() {
+            layer.on_detach();
         }
     }
 }
 
 pub trait Layer {
     fn get_name(&mut self) -> &String;
+    /// Called by the [`LayerStack`] right after the layer is pushed.
     fn on_attach(&mut self);
+    /// Called by the [`LayerStack`] when the layer is removed or the stack is dropped.
     fn on_detach(&mut self);
-    fn on_update(&mut self);
-    fn on_event(&mut self, event: &dyn Eventable);
+    /// Called once per frame with the time the previous frame took.
+    fn on_update(&mut self, timestep: Timestep);
+    /// Called at a fixed rate, zero or more times per frame, before `on_update`.
+    /// Physics and gameplay that must not depend on frame rate belong here.
+    fn on_fixed_update(&mut self, _timestep: Timestep) {}
+    /// Called for events that no layer above this one has handled.
+    /// Marking the event as handled stops it from reaching the layers below.
+    fn on_event(&mut self, event: &mut dyn Eventable);
+    /// Events outside of these categories skip this layer, by default it gets every event.
+    fn get_event_categories(&self) -> EventCategoryFlags {
+        EventCategoryFlags::ALL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::core::test_utils::{Log, LoggingLayer};
+
+    fn bottom_up_names(stack: &mut LayerStack) -> Vec<String> {
+        stack
+            .iter_bottom_up_mut()
+            .map(|layer| layer.get_name().clone())
+            .collect()
+    }
+
+    fn top_down_names(stack: &mut LayerStack) -> Vec<String> {
+        stack
+            .iter_top_down_mut()
+            .map(|layer| layer.get_name().clone())
+            .collect()
+    }
+
+    fn name_of(layer: Option<Box<dyn Layer>>) -> Option<String> {
+        layer.map(|mut layer| layer.get_name().clone())
+    }
+
+    #[test]
+    fn overlays_stay_above_layers() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        stack.push_layer(LoggingLayer::boxed("a", &log));
+        stack.push_overlay(LoggingLayer::boxed("o1", &log));
+        stack.push_layer(LoggingLayer::boxed("b", &log));
+        stack.push_overlay(LoggingLayer::boxed("o2", &log));
+
+        assert_eq!(bottom_up_names(&mut stack), ["a", "b", "o1", "o2"]);
+        assert_eq!(top_down_names(&mut stack), ["o2", "o1", "b", "a"]);
+        assert_eq!(stack.get_layer_count(), 2);
+        assert_eq!(stack.get_overlay_count(), 2);
+        assert_eq!(stack.len(), 4);
+    }
+
+    #[test]
+    fn pop_layer_removes_topmost_layer_and_keeps_overlays() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        stack.push_layer(LoggingLayer::boxed("a", &log));
+        stack.push_layer(LoggingLayer::boxed("b", &log));
+        stack.push_overlay(LoggingLayer::boxed("o", &log));
+
+        assert_eq!(name_of(stack.pop_layer()).as_deref(), Some("b"));
+        assert_eq!(bottom_up_names(&mut stack), ["a", "o"]);
+        assert_eq!(name_of(stack.pop_layer()).as_deref(), Some("a"));
+        assert!(stack.pop_layer().is_none());
+        assert_eq!(bottom_up_names(&mut stack), ["o"]);
+    }
+
+    #[test]
+    fn pop_on_empty_regions_returns_none() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        assert!(stack.pop_layer().is_none());
+        assert!(stack.pop_overlay().is_none());
+
+        stack.push_overlay(LoggingLayer::boxed("o", &log));
+        assert!(stack.pop_layer().is_none());
+        stack.push_layer(LoggingLayer::boxed("a", &log));
+        assert_eq!(name_of(stack.pop_overlay()).as_deref(), Some("o"));
+        assert!(stack.pop_overlay().is_none());
+        assert_eq!(bottom_up_names(&mut stack), ["a"]);
+    }
+
+    #[test]
+    fn pop_overlay_removes_topmost_overlay() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        stack.push_overlay(LoggingLayer::boxed("o1", &log));
+        stack.push_overlay(LoggingLayer::boxed("o2", &log));
+        stack.push_layer(LoggingLayer::boxed("a", &log));
+
+        assert_eq!(name_of(stack.pop_overlay()).as_deref(), Some("o2"));
+        assert_eq!(bottom_up_names(&mut stack), ["a", "o1"]);
+    }
+
+    #[test]
+    fn push_attaches_and_removal_detaches() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        let a = stack.push_layer(LoggingLayer::boxed("a", &log));
+        stack.push_overlay(LoggingLayer::boxed("o", &log));
+        stack.pop_overlay();
+        stack.remove(a);
+
+        assert_eq!(
+            *log.borrow(),
+            ["attach a", "attach o", "detach o", "detach a"]
+        );
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn remove_by_handle_and_name() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        let a = stack.push_layer(LoggingLayer::boxed("a", &log));
+        stack.push_layer(LoggingLayer::boxed("shared", &log));
+        let overlay = stack.push_overlay(LoggingLayer::boxed("shared", &log));
+        let b = stack.push_layer(LoggingLayer::boxed("b", &log));
+
+        assert!(stack.remove(a).is_some());
+        assert!(stack.remove(a).is_none());
+        assert!(!stack.contains(a));
+        assert_eq!(bottom_up_names(&mut stack), ["shared", "b", "shared"]);
+
+        // The overlay is above the layer of the same name.
+        assert!(stack.remove_by_name("shared").is_some());
+        assert!(!stack.contains(overlay));
+        assert_eq!(bottom_up_names(&mut stack), ["shared", "b"]);
+        assert!(stack.remove_by_name("missing").is_none());
+        assert!(stack.contains(b));
+    }
+
+    #[test]
+    fn lookup_by_handle_and_name() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        let a = stack.push_layer(LoggingLayer::boxed("a", &log));
+        stack.push_overlay(LoggingLayer::boxed("o", &log));
+
+        assert_eq!(
+            stack.get(a).map(|l| l.get_name().clone()).as_deref(),
+            Some("a")
+        );
+        assert!(stack.get_by_name("o").is_some());
+        assert!(stack.get_by_name("missing").is_none());
+        stack.remove(a);
+        assert!(stack.get(a).is_none());
+    }
+
+    #[test]
+    fn deferred_commands_apply_in_order() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        let a = stack.push_layer(LoggingLayer::boxed("a", &log));
+        let commands = stack.get_commands();
+
+        let x = commands.push_layer(LoggingLayer::boxed("x", &log));
+        commands.push_overlay(LoggingLayer::boxed("y", &log));
+        commands.remove(a);
+        assert_eq!(bottom_up_names(&mut stack), ["a"]);
+        assert!(!stack.contains(x));
+
+        stack.apply_commands();
+        assert_eq!(bottom_up_names(&mut stack), ["x", "y"]);
+        assert!(stack.contains(x));
+
+        commands.pop_overlay();
+        commands.pop_layer();
+        stack.apply_commands();
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn layers_can_push_layers_from_on_update() {
+        let log = Log::default();
+        let mut stack = LayerStack::new();
+        let commands = stack.get_commands();
+        let pushed_log = log.clone();
+        stack.push_layer(Box::new(LoggingLayer {
+            on_update: Some(Box::new(move |_| {
+                commands.push_layer(LoggingLayer::boxed("spawned", &pushed_log));
+            })),
+            ..LoggingLayer::new("spawner", &log)
+        }));
+
+        let timestep = Timestep::new(Duration::ZERO, Duration::ZERO, 0.0);
+        for layer in stack.iter_bottom_up_mut() {
+            layer.on_update(timestep);
+        }
+        assert_eq!(stack.len(), 1);
+
+        stack.apply_commands();
+        assert_eq!(bottom_up_names(&mut stack), ["spawner", "spawned"]);
+        assert_eq!(*log.borrow(), ["attach spawner", "attach spawned"]);
+    }
+
+    #[test]
+    fn drop_detaches_top_down() {
+        let log = Log::default();
+        {
+            let mut stack = LayerStack::new();
+            stack.push_layer(LoggingLayer::boxed("a", &log));
+            stack.push_overlay(LoggingLayer::boxed("o", &log));
+            stack.push_layer(LoggingLayer::boxed("b", &log));
+        }
+
+        assert_eq!(log.borrow()[3..], ["detach o", "detach b", "detach a"]);
+    }
 }
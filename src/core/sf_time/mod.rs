@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+/// Timing information handed to layers and systems on every update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestep {
+    delta: Duration,
+    elapsed: Duration,
+    alpha: f32,
+}
+
+impl Timestep {
+    pub fn new(delta: Duration, elapsed: Duration, alpha: f32) -> Self {
+        Self {
+            delta,
+            elapsed,
+            alpha,
+        }
+    }
+
+    /// Time since the previous update of the same kind.
+    pub fn get_delta(&self) -> Duration {
+        self.delta
+    }
+    pub fn get_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+    pub fn get_milliseconds(&self) -> f32 {
+        self.delta.as_secs_f32() * 1000.0
+    }
+
+    /// Total simulated time up to this update.
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// How far the frame is between the last fixed update and the next one, in `0.0..1.0`.
+    /// Used to interpolate state written in fixed updates when rendering. Always `0.0` for fixed updates.
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+}
+
+/// What a frame has to run: the variable update and any fixed updates that became due.
+#[derive(Debug, Clone)]
+pub struct FrameTiming {
+    pub timestep: Timestep,
+    pub fixed_timesteps: Vec<Timestep>,
+}
+
+/// Measures frame time and accumulates it into fixed size steps.
+pub struct FrameClock {
+    fixed_step: Duration,
+    max_fixed_steps_per_frame: u32,
+    accumulator: Duration,
+    last_frame: Option<Instant>,
+    elapsed: Duration,
+    fixed_elapsed: Duration,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new(Duration::from_secs_f64(1.0 / 60.0))
+    }
+}
+
+impl FrameClock {
+    pub fn new(fixed_step: Duration) -> Self {
+        Self {
+            fixed_step,
+            max_fixed_steps_per_frame: 8,
+            accumulator: Duration::ZERO,
+            last_frame: None,
+            elapsed: Duration::ZERO,
+            fixed_elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn get_fixed_step(&self) -> Duration {
+        self.fixed_step
+    }
+    pub fn set_fixed_step(&mut self, fixed_step: Duration) {
+        self.fixed_step = fixed_step;
+    }
+
+    pub fn get_max_fixed_steps_per_frame(&self) -> u32 {
+        self.max_fixed_steps_per_frame
+    }
+    /// Caps how many fixed updates one frame may run. Time beyond that is dropped,
+    /// so a slow frame doesn't make the next one even slower.
+    pub fn set_max_fixed_steps_per_frame(&mut self, max_fixed_steps_per_frame: u32) {
+        self.max_fixed_steps_per_frame = max_fixed_steps_per_frame;
+    }
+
+    /// Starts a new frame measured against the wall clock. The first frame has zero delta.
+    pub fn tick(&mut self) -> FrameTiming {
+        let now = Instant::now();
+        let delta = self
+            .last_frame
+            .map(|last_frame| now.duration_since(last_frame))
+            .unwrap_or(Duration::ZERO);
+        self.last_frame = Some(now);
+
+        self.advance(delta)
+    }
+
+    /// Starts a new frame that took `delta`, without looking at the wall clock.
+    pub fn advance(&mut self, delta: Duration) -> FrameTiming {
+        self.elapsed += delta;
+        self.accumulator += delta;
+
+        let mut fixed_timesteps = Vec::new();
+        if !self.fixed_step.is_zero() {
+            while self.accumulator >= self.fixed_step {
+                if fixed_timesteps.len() as u32 >= self.max_fixed_steps_per_frame {
+                    self.accumulator = Duration::ZERO;
+                    break;
+                }
+                self.accumulator -= self.fixed_step;
+                self.fixed_elapsed += self.fixed_step;
+                fixed_timesteps.push(Timestep::new(self.fixed_step, self.fixed_elapsed, 0.0));
+            }
+        }
+
+        let alpha = if self.fixed_step.is_zero() {
+            0.0
+        } else {
+            self.accumulator.as_secs_f32() / self.fixed_step.as_secs_f32()
+        };
+
+        FrameTiming {
+            timestep: Timestep::new(delta, self.elapsed, alpha),
+            fixed_timesteps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn accumulates_time_into_fixed_steps() {
+        let mut clock = FrameClock::new(STEP);
+
+        let timing = clock.advance(Duration::from_millis(25));
+        assert_eq!(timing.fixed_timesteps.len(), 2);
+        assert_eq!(timing.fixed_timesteps[1].get_delta(), STEP);
+        assert_eq!(timing.fixed_timesteps[1].get_elapsed(), 2 * STEP);
+        assert_eq!(timing.fixed_timesteps[1].get_alpha(), 0.0);
+        assert_eq!(timing.timestep.get_delta(), Duration::from_millis(25));
+        assert!((timing.timestep.get_alpha() - 0.5).abs() < 1e-4);
+
+        // The leftover 5ms carries over into the next frame.
+        let timing = clock.advance(Duration::from_millis(7));
+        assert_eq!(timing.fixed_timesteps.len(), 1);
+        assert_eq!(timing.timestep.get_elapsed(), Duration::from_millis(32));
+        assert!((timing.timestep.get_alpha() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn drops_time_beyond_the_max_fixed_steps() {
+        let mut clock = FrameClock::new(STEP);
+        clock.set_max_fixed_steps_per_frame(3);
+
+        let timing = clock.advance(Duration::from_millis(105));
+        assert_eq!(timing.fixed_timesteps.len(), 3);
+        assert_eq!(timing.timestep.get_alpha(), 0.0);
+        assert_eq!(timing.timestep.get_elapsed(), Duration::from_millis(105));
+
+        let timing = clock.advance(Duration::from_millis(5));
+        assert!(timing.fixed_timesteps.is_empty());
+        assert_eq!(timing.timestep.get_elapsed(), Duration::from_millis(110));
+    }
+
+    #[test]
+    fn zero_fixed_step_runs_no_fixed_updates() {
+        let mut clock = FrameClock::new(Duration::ZERO);
+
+        let timing = clock.advance(Duration::from_millis(16));
+        assert!(timing.fixed_timesteps.is_empty());
+        assert_eq!(timing.timestep.get_alpha(), 0.0);
+        assert_eq!(timing.timestep.get_delta(), Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,142 @@
+//! Fixtures shared by the unit tests of events, layers and the event system.
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::{
+    sf_events::{CategoryListener, EventCategoryFlags, EventListener, Eventable},
+    sf_layers::Layer,
+    sf_time::Timestep,
+};
+
+/// What the fixtures saw, in order.
+pub type Log = Rc<RefCell<Vec<String>>>;
+
+/// User event that only has a name.
+pub struct TestEvent {
+    pub name: String,
+    pub is_handled: bool,
+}
+
+impl TestEvent {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            is_handled: false,
+        }
+    }
+}
+
+impl Eventable for TestEvent {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn is_handled(&self) -> bool {
+        self.is_handled
+    }
+    fn set_handled(&mut self, is_handled: bool) {
+        self.is_handled = is_handled;
+    }
+}
+
+/// Logs "attach <name>", "detach <name>" and "<name> <event>" for the events of `categories`.
+pub struct LoggingLayer {
+    pub name: String,
+    pub log: Log,
+    pub categories: EventCategoryFlags,
+    /// Marks every event it gets as handled.
+    pub handles_events: bool,
+    /// Also logs "<name> update <timestep>" and "<name> fixed update <timestep>".
+    pub logs_updates: bool,
+    /// Runs on every update, after it was logged.
+    pub on_update: Option<Box<dyn FnMut(Timestep)>>,
+}
+
+impl LoggingLayer {
+    pub fn new(name: &str, log: &Log) -> Self {
+        Self {
+            name: name.to_string(),
+            log: log.clone(),
+            categories: EventCategoryFlags::ALL,
+            handles_events: false,
+            logs_updates: false,
+            on_update: None,
+        }
+    }
+
+    pub fn boxed(name: &str, log: &Log) -> Box<dyn Layer> {
+        Box::new(Self::new(name, log))
+    }
+}
+
+impl Layer for LoggingLayer {
+    fn get_name(&mut self) -> &String {
+        &self.name
+    }
+    fn on_attach(&mut self) {
+        self.log.borrow_mut().push(format!("attach {}", self.name));
+    }
+    fn on_detach(&mut self) {
+        self.log.borrow_mut().push(format!("detach {}", self.name));
+    }
+    fn on_update(&mut self, timestep: Timestep) {
+        if self.logs_updates {
+            self.log
+                .borrow_mut()
+                .push(format!("{} update {:?}", self.name, timestep));
+        }
+        if let Some(on_update) = &mut self.on_update {
+            on_update(timestep);
+        }
+    }
+    fn on_fixed_update(&mut self, timestep: Timestep) {
+        if self.logs_updates {
+            self.log
+                .borrow_mut()
+                .push(format!("{} fixed update {:?}", self.name, timestep));
+        }
+    }
+    fn on_event(&mut self, event: &mut dyn Eventable) {
+        self.log
+            .borrow_mut()
+            .push(format!("{} {}", self.name, event.to_string()));
+        if self.handles_events {
+            event.set_handled(true);
+        }
+    }
+    fn get_event_categories(&self) -> EventCategoryFlags {
+        self.categories
+    }
+}
+
+/// Logs its name for every [`TestEvent`], or every event of its category.
+pub struct LoggingListener {
+    pub name: &'static str,
+    pub log: Log,
+    pub handles: bool,
+}
+
+impl LoggingListener {
+    pub fn new(name: &'static str, log: &Log) -> Self {
+        Self {
+            name,
+            log: log.clone(),
+            handles: false,
+        }
+    }
+}
+
+impl EventListener for LoggingListener {
+    type EventableConcreteType = TestEvent;
+
+    fn handle(&mut self, _event: &Self::EventableConcreteType) -> bool {
+        self.log.borrow_mut().push(self.name.to_string());
+        self.handles
+    }
+}
+
+impl CategoryListener for LoggingListener {
+    fn handle(&mut self, _event: &dyn Eventable) -> bool {
+        self.log.borrow_mut().push(self.name.to_string());
+        self.handles
+    }
+}
@@ -0,0 +1,198 @@
+use glam::Mat4;
+
+use super::{Entity, Transform, World};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        fov_y_radians: f32,
+        near: f32,
+        far: f32,
+    },
+    /// `height` is the visible extent along the y axis in world units,
+    /// the width follows from the aspect ratio.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn get_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective {
+                fov_y_radians,
+                near,
+                far,
+            } => Mat4::perspective_rh(fov_y_radians, aspect_ratio, near, far),
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        }
+    }
+}
+
+/// Views the world from the [`Transform`] of the entity it is attached to, looking down -Z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub projection: Projection,
+    pub aspect_ratio: f32,
+    /// The world is rendered through the first active camera found.
+    pub is_active: bool,
+}
+
+impl Camera {
+    pub fn perspective(fov_y_radians: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Perspective {
+                fov_y_radians,
+                near,
+                far,
+            },
+            aspect_ratio: 1.0,
+            is_active: true,
+        }
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { height, near, far },
+            aspect_ratio: 1.0,
+            is_active: true,
+        }
+    }
+
+    pub fn get_view_matrix(transform: &Transform) -> Mat4 {
+        Mat4::from_rotation_translation(transform.rotation, transform.position).inverse()
+    }
+
+    pub fn get_projection_matrix(&self) -> Mat4 {
+        self.projection.get_matrix(self.aspect_ratio)
+    }
+
+    pub fn get_view_projection_matrix(&self, transform: &Transform) -> Mat4 {
+        self.get_projection_matrix() * Self::get_view_matrix(transform)
+    }
+}
+
+impl World {
+    pub fn get_active_camera(&self) -> Option<(Entity, &Camera, &Transform)> {
+        self.query::<(&Camera, &Transform)>()
+            .find(|(_, (camera, _))| camera.is_active)
+            .map(|(entity, (camera, transform))| (entity, camera, transform))
+    }
+
+    pub fn get_aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    /// Updates every camera, including ones inserted later. Called with the size of the
+    /// render target whenever it is resized.
+    pub fn set_camera_aspect_ratio(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let aspect_ratio = width as f32 / height as f32;
+        self.aspect_ratio = aspect_ratio;
+        for (_, camera) in self.query_mut::<&mut Camera>() {
+            camera.aspect_ratio = aspect_ratio;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, f32::consts::FRAC_PI_2, rc::Rc};
+
+    use glam::{Quat, Vec3, Vec4Swizzles};
+
+    use super::*;
+    use crate::core::sf_graphics::wgpu_backend::WgpuGraphics;
+
+    fn project(matrix: Mat4, point: Vec3) -> Vec3 {
+        let clip = matrix * point.extend(1.0);
+        clip.xyz() / clip.w
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn perspective_maps_the_frustum_to_clip_space() {
+        let camera = Camera {
+            aspect_ratio: 2.0,
+            ..Camera::perspective(FRAC_PI_2, 1.0, 10.0)
+        };
+        let projection = camera.get_projection_matrix();
+
+        // With a 90 degree fov the frustum edges run at 45 degrees, the width is doubled by the aspect.
+        assert_near(project(projection, Vec3::new(0.0, 1.0, -1.0)), Vec3::Y);
+        assert_near(project(projection, Vec3::new(2.0, 0.0, -1.0)), Vec3::X);
+        assert_near(project(projection, Vec3::new(0.0, 0.0, -10.0)), Vec3::Z);
+        assert_near(
+            project(projection, Vec3::new(0.0, 5.0, -5.0)).with_z(0.0),
+            Vec3::Y,
+        );
+    }
+
+    #[test]
+    fn orthographic_height_is_kept_and_width_follows_the_aspect() {
+        let camera = Camera {
+            aspect_ratio: 1.5,
+            ..Camera::orthographic(4.0, 0.0, 10.0)
+        };
+        let projection = camera.get_projection_matrix();
+
+        assert_near(
+            project(projection, Vec3::new(3.0, 2.0, 0.0)),
+            Vec3::new(1.0, 1.0, 0.0),
+        );
+        assert_near(
+            project(projection, Vec3::new(-3.0, -2.0, -10.0)),
+            Vec3::new(-1.0, -1.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn view_matrix_moves_the_world_in_front_of_the_camera() {
+        let transform = Transform {
+            rotation: Quat::from_rotation_y(FRAC_PI_2),
+            ..Transform::from_position(Vec3::new(1.0, 2.0, 3.0))
+        };
+        let view = Camera::get_view_matrix(&transform);
+
+        assert_near(view.transform_point3(transform.position), Vec3::ZERO);
+        // Turned left by 90 degrees the camera looks down -X.
+        assert_near(
+            view.transform_point3(Vec3::new(-4.0, 2.0, 3.0)),
+            Vec3::new(0.0, 0.0, -5.0),
+        );
+    }
+
+    #[test]
+    fn cameras_inserted_after_a_resize_get_the_aspect_ratio() {
+        let Some(graphics) = WgpuGraphics::headless_for_tests(4) else {
+            return;
+        };
+        let mut world = World::new(Rc::new(RefCell::new(graphics)));
+        let first = world.spawn();
+        world.insert(first, Camera::perspective(FRAC_PI_2, 0.1, 10.0));
+
+        world.set_camera_aspect_ratio(300, 200);
+        world.set_camera_aspect_ratio(0, 200);
+        let second = world.spawn();
+        world.insert(second, Camera::orthographic(2.0, 0.1, 10.0));
+
+        assert_eq!(world.get_aspect_ratio(), 1.5);
+        assert_eq!(world.get::<Camera>(first).unwrap().aspect_ratio, 1.5);
+        assert_eq!(world.get::<Camera>(second).unwrap().aspect_ratio, 1.5);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use super::{
+    entity::{Entities, Entity},
+    query::{QueryData, QueryIter, ReadOnlyQueryData},
+};
+
+/// Anything `'static` can be attached to an entity as a component.
+pub trait Component: 'static {}
+impl<T: 'static> Component for T {}
+
+/// Sparse set storage for one component type. Components are packed densely so
+/// queries iterate over contiguous memory, the sparse array maps entity index to dense slot.
+pub struct ComponentStorage<T: Component> {
+    dense: Vec<T>,
+    entities: Vec<Entity>,
+    sparse: Vec<Option<usize>>,
+}
+
+impl<T: Component> Default for ComponentStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Component> ComponentStorage<T> {
+    pub fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            entities: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+
+    /// Returns the previous component of the entity, if any.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        let index = entity.get_index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+
+        match self.sparse[index] {
+            Some(slot) => {
+                self.entities[slot] = entity;
+                Some(std::mem::replace(&mut self.dense[slot], component))
+            }
+            None => {
+                self.sparse[index] = Some(self.dense.len());
+                self.dense.push(component);
+                self.entities.push(entity);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slot(entity)?;
+        self.sparse[entity.get_index() as usize] = None;
+
+        let component = self.dense.swap_remove(slot);
+        self.entities.swap_remove(slot);
+        if let Some(moved) = self.entities.get(slot) {
+            self.sparse[moved.get_index() as usize] = Some(slot);
+        }
+
+        Some(component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.slot(entity).map(|slot| &self.dense[slot])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.slot(entity).map(|slot| &mut self.dense[slot])
+    }
+
+    /// Pointer to the entity's component that doesn't borrow the rest of the storage,
+    /// so queries can hand out mutable references to several entities at once.
+    ///
+    /// # Safety
+    /// `this` must point to a live storage that is not mutably borrowed elsewhere.
+    pub(super) unsafe fn get_ptr(this: *mut Self, entity: Entity) -> Option<*mut T> {
+        unsafe {
+            let slot = (*this).slot(entity)?;
+            Some((*this).dense.as_mut_ptr().add(slot))
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.slot(entity).is_some()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    fn slot(&self, entity: Entity) -> Option<usize> {
+        let slot = (*self.sparse.get(entity.get_index() as usize)?)?;
+        (self.entities[slot] == entity).then_some(slot)
+    }
+}
+
+/// Type erased [`ComponentStorage`], lets the world drop components of despawned entities
+/// without knowing their types.
+pub trait AnyStorage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Component> AnyStorage for ComponentStorage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The entities of a [`super::World`] and their components, one storage per component type.
+#[derive(Default)]
+pub struct Components {
+    entities: Entities,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl Components {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.allocate()
+    }
+
+    /// Returns `false` if the entity was already despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    /// Hands the component back if the entity is despawned, otherwise returns the one it replaced.
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.entities.is_alive(entity) {
+            return Some(component);
+        }
+
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(ComponentStorage::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<ComponentStorage<T>>()
+            .expect("component storage registered under a foreign TypeId")
+            .insert(entity, component)
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>()?.remove(entity)
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.storage::<T>()?.get(entity)
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage_mut::<T>()?.get_mut(entity)
+    }
+
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.storage::<T>().is_some_and(|s| s.contains(entity))
+    }
+
+    pub fn storage<T: Component>(&self) -> Option<&ComponentStorage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<ComponentStorage<T>>()
+    }
+
+    pub fn storage_mut<T: Component>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<ComponentStorage<T>>()
+    }
+
+    pub fn query<Q: ReadOnlyQueryData>(&self) -> QueryIter<'_, Q> {
+        // Read only queries never write through the pointer.
+        unsafe { QueryIter::new(self as *const Components as *mut Components) }
+    }
+
+    /// Panics if the same component is requested twice and one of the requests is mutable.
+    pub fn query_mut<Q: QueryData>(&mut self) -> QueryIter<'_, Q> {
+        let mut access = Vec::new();
+        Q::access(&mut access);
+        for (i, (type_id, mutable)) in access.iter().enumerate() {
+            let aliased = access[i + 1..].iter().any(|(other_id, other_mutable)| {
+                other_id == type_id && (*mutable || *other_mutable)
+            });
+            assert!(
+                !aliased,
+                "query {} borrows a component mutably more than once",
+                std::any::type_name::<Q>()
+            );
+        }
+
+        unsafe { QueryIter::new(self as *mut Components) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[test]
+    fn storage_replaces_and_swap_removes_keeping_sparse_indices() {
+        let mut entities = Entities::new();
+        let [a, b, c] = [(); 3].map(|_| entities.allocate());
+        let mut storage = ComponentStorage::new();
+
+        assert_eq!(storage.insert(a, Health(1)), None);
+        assert_eq!(storage.insert(b, Health(2)), None);
+        assert_eq!(storage.insert(c, Health(3)), None);
+        assert_eq!(storage.insert(b, Health(20)), Some(Health(2)));
+        assert_eq!(storage.len(), 3);
+
+        // `c` is moved into the slot `a` leaves behind.
+        assert_eq!(storage.remove(a), Some(Health(1)));
+        assert_eq!(storage.remove(a), None);
+        assert_eq!(storage.entities(), [c, b]);
+        assert_eq!(storage.get(a), None);
+        assert_eq!(storage.get(b), Some(&Health(20)));
+        assert_eq!(storage.get(c), Some(&Health(3)));
+
+        // Removing the last slot moves nothing.
+        assert_eq!(storage.remove(b), Some(Health(20)));
+        assert_eq!(storage.get(c), Some(&Health(3)));
+        assert_eq!(storage.len(), 1);
+    }
+
+    #[test]
+    fn despawn_makes_handles_stale() {
+        let mut components = Components::new();
+        let entity = components.spawn();
+        components.insert(entity, Health(1));
+
+        assert!(components.despawn(entity));
+        assert!(!components.despawn(entity));
+        assert!(!components.is_alive(entity));
+        assert_eq!(components.get::<Health>(entity), None);
+
+        // The index is reused under a new generation, the old handle still sees nothing.
+        let reused = components.spawn();
+        assert_eq!(reused.get_index(), entity.get_index());
+        assert_ne!(reused.get_generation(), entity.get_generation());
+        assert_eq!(components.insert(entity, Health(2)), Some(Health(2)));
+        components.insert(reused, Health(3));
+        assert_eq!(components.get::<Health>(entity), None);
+        assert_eq!(components.get::<Health>(reused), Some(&Health(3)));
+        assert!(!components.has::<Health>(entity));
+        assert_eq!(components.entities().len(), 1);
+    }
+}
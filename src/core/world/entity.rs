@@ -0,0 +1,92 @@
+/// Handle to an object living in a [`super::World`].
+///
+/// The generation makes handles of despawned entities stale, so they never alias
+/// an entity that later reuses the same index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+#[derive(Default)]
+pub struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    count: usize,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allocate(&mut self) -> Entity {
+        self.count += 1;
+
+        if let Some(index) = self.free_indices.pop() {
+            self.alive[index as usize] = true;
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+
+        let index = self.generations.len() as u32;
+        self.generations.push(0);
+        self.alive.push(true);
+        Entity {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Returns `false` if the entity was already freed.
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_indices.push(entity.index);
+        self.count -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len()
+            && self.alive[index]
+            && self.generations[index] == entity.generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(index, _)| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+}
@@ -0,0 +1,157 @@
+use std::{mem, ops::Range};
+
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coords: [f32; 2],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+
+    /// Vertex with only a position, normal facing +Z and zero UVs.
+    pub fn from_position(position: [f32; 3]) -> Self {
+        Self {
+            position,
+            normal: [0.0, 0.0, 1.0],
+            tex_coords: [0.0, 0.0],
+        }
+    }
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Vertex buffer formats a [`Mesh`] can be uploaded with, part of the render pipeline cache key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VertexLayout {
+    /// [`Vertex`]: position, normal and UVs.
+    #[default]
+    Standard,
+}
+
+impl VertexLayout {
+    pub fn get_buffer_layout(&self) -> wgpu::VertexBufferLayout<'static> {
+        match self {
+            VertexLayout::Standard => Vertex::layout(),
+        }
+    }
+}
+
+/// Part of a mesh drawn with one draw call, e.g. one glTF primitive or one OBJ object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubMesh {
+    pub name: String,
+    pub index_range: Range<u32>,
+    pub base_vertex: i32,
+    /// Material index inside the source file, if it assigned one.
+    pub material_index: Option<usize>,
+}
+
+/// Geometry on the CPU side, as produced by the mesh loaders.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub submeshes: Vec<SubMesh>,
+}
+
+impl MeshData {
+    /// Appends a primitive as a new submesh. `indices` are relative to `vertices`.
+    pub fn push_submesh(
+        &mut self,
+        name: String,
+        vertices: &[Vertex],
+        indices: &[u32],
+        material_index: Option<usize>,
+    ) {
+        let base_vertex = self.vertices.len() as i32;
+        let first_index = self.indices.len() as u32;
+
+        self.vertices.extend_from_slice(vertices);
+        self.indices.extend_from_slice(indices);
+        self.submeshes.push(SubMesh {
+            name,
+            index_range: first_index..self.indices.len() as u32,
+            base_vertex,
+            material_index,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Replaces normals with area weighted vertex normals computed from the triangles.
+    pub fn compute_normals(vertices: &mut [Vertex], indices: &[u32]) {
+        let mut normals = vec![Vec3::ZERO; vertices.len()];
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+            let (pa, pb, pc) = (
+                Vec3::from(vertices[a].position),
+                Vec3::from(vertices[b].position),
+                Vec3::from(vertices[c].position),
+            );
+            let face_normal = (pb - pa).cross(pc - pa);
+            normals[a] += face_normal;
+            normals[b] += face_normal;
+            normals[c] += face_normal;
+        }
+
+        for (vertex, normal) in vertices.iter_mut().zip(normals) {
+            vertex.normal = normal.normalize_or(Vec3::Z).to_array();
+        }
+    }
+}
+
+/// Geometry uploaded to the GPU. Buffers are reference counted by wgpu,
+/// so cloning a mesh to share it between entities is cheap.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_elements: u32,
+    pub submeshes: Vec<SubMesh>,
+    pub vertex_layout: VertexLayout,
+}
+
+impl Mesh {
+    /// Uploads a mesh made of a single submesh.
+    pub fn new(device: &wgpu::Device, vertices: &[Vertex], indices: &[u32]) -> Self {
+        let mut data = MeshData::default();
+        data.push_submesh(String::new(), vertices, indices, None);
+        Self::from_data(device, &data)
+    }
+
+    pub fn from_data(device: &wgpu::Device, data: &MeshData) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Vertex buffer"),
+            contents: bytemuck::cast_slice(&data.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Indices buffer"),
+            contents: bytemuck::cast_slice(&data.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_elements: data.indices.len() as u32,
+            submeshes: data.submeshes.clone(),
+            vertex_layout: VertexLayout::Standard,
+        }
+    }
+}
@@ -1,146 +1,461 @@
-use std::{cell::RefCell, mem, rc::Rc};
+pub mod camera;
+pub use camera::*;
+pub mod component;
+pub use component::*;
+pub mod entity;
+pub use entity::*;
+pub mod mesh;
+pub use mesh::*;
+pub mod query;
+pub use query::*;
+pub mod system;
+pub use system::*;
+pub mod transform;
+pub use transform::*;
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use wgpu::util::DeviceExt;
 
+use crate::{error_core, info_core, warn_core};
+
 use super::{
-    sf_events::EventDispatcher, sf_graphics::wgpu_backend::WgpuGraphics, sf_layers::Layer,
+    sf_assets::get_resource_path,
+    sf_error::SfResult,
+    sf_events::{
+        EventCategory, EventCategoryFlags, EventDispatcher, EventListener,
+        WindowRedrawRequestedEvent,
+    },
+    sf_graphics::{
+        material::{
+            Material, MaterialDescriptor, MaterialUniform, PipelineCache, ShaderId, ShaderLibrary,
+        },
+        texture::Texture,
+        wgpu_backend::WgpuGraphics,
+        world_graphics::{CameraUniform, render_world_to_texture},
+    },
+    sf_layers::Layer,
+    sf_time::Timestep,
 };
 
+/// Shaders are loaded from here at runtime and reloaded when they change.
+pub fn get_shader_directory() -> PathBuf {
+    get_resource_path("shaders")
+}
+
 pub struct WorldLayerWrapper<'a> {
     name: String,
+    world: Rc<RefCell<World>>,
+    graphics: Rc<RefCell<WgpuGraphics>>,
     event_dispatcher: EventDispatcher<'a>,
 }
 
+impl<'a> WorldLayerWrapper<'a> {
+    pub fn new(
+        name: String,
+        world: Rc<RefCell<World>>,
+        graphics: Rc<RefCell<WgpuGraphics>>,
+    ) -> Self {
+        let mut event_dispatcher = EventDispatcher::new();
+        event_dispatcher
+            .add_listener(WorldPreviewRedrawListener {
+                world: world.clone(),
+                graphics: graphics.clone(),
+            })
+            .forget();
+
+        Self {
+            name,
+            world,
+            graphics,
+            event_dispatcher,
+        }
+    }
+}
+
+/// Renders the world into every secondary window, e.g. a game preview opened from the editor.
+struct WorldPreviewRedrawListener {
+    world: Rc<RefCell<World>>,
+    graphics: Rc<RefCell<WgpuGraphics>>,
+}
+impl EventListener for WorldPreviewRedrawListener {
+    type EventableConcreteType = WindowRedrawRequestedEvent;
+
+    fn handle(&mut self, event: &Self::EventableConcreteType) -> bool {
+        let window_id = *event.get_window_id();
+        let graphics = self.graphics.borrow();
+        if graphics.main_window == Some(window_id) {
+            return false;
+        }
+        let Some(window_surface) = graphics.get_surface(window_id) else {
+            return false;
+        };
+
+        match graphics.acquire_window_texture(window_id) {
+            Ok(Some(surface_texture)) => {
+                let view = surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                // The world's aspect ratio follows the editor's view, not this window.
+                let aspect_ratio =
+                    window_surface.config.width as f32 / window_surface.config.height.max(1) as f32;
+                render_world_to_texture(
+                    &self.world.borrow(),
+                    &view,
+                    window_surface.config.format,
+                    &window_surface.depth_texture,
+                    Some(aspect_ratio),
+                    &graphics,
+                );
+                surface_texture.present();
+            }
+            Ok(None) => {}
+            Err(e) => error_core!("PREVIEW FRAME FAILED: {}", e),
+        }
+        false
+    }
+}
+
 impl<'a> Layer for WorldLayerWrapper<'a> {
     fn get_name(&mut self) -> &String {
         &self.name
     }
 
-    fn on_attach(&mut self) {
-        todo!()
+    fn on_attach(&mut self) {}
+
+    fn on_detach(&mut self) {}
+
+    fn on_update(&mut self, timestep: Timestep) {
+        let mut world = self.world.borrow_mut();
+        world.reload_changed_shaders(&self.graphics.borrow().device);
+        world.on_update(timestep);
     }
 
-    fn on_detach(&mut self) {
-        todo!()
+    fn on_fixed_update(&mut self, timestep: Timestep) {
+        self.world.borrow_mut().on_fixed_update(timestep);
     }
 
-    fn on_update(&mut self) {}
+    fn on_event(&mut self, event: &mut dyn super::sf_events::Eventable) {
+        self.event_dispatcher.dispatch_dynamic(event);
+    }
 
-    fn on_event(&mut self, event: &dyn super::sf_events::Eventable) {}
+    /// Only window redraws are handled here, input is read through polling.
+    fn get_event_categories(&self) -> EventCategoryFlags {
+        EventCategory::ApplicationCategory.into()
+    }
 }
 
 pub struct WorldLayer {}
 
 pub struct World {
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub num_elements: u32,
-    pub render_pipeline: wgpu::RenderPipeline,
+    components: Components,
+    /// Of the render target the world is shown in, given to every camera.
+    aspect_ratio: f32,
+    systems: Vec<Box<dyn System>>,
+    fixed_systems: Vec<Box<dyn System>>,
+    pub camera_buffer: wgpu::Buffer,
+    pub camera_bind_group: wgpu::BindGroup,
+    pub material_bind_group_layout: wgpu::BindGroupLayout,
+    pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    /// Bound for materials without a texture, a 1x1 white texture.
+    pub default_texture: Rc<Texture>,
+    pub shaders: ShaderLibrary,
+    pub pipelines: PipelineCache,
+    /// The built in `shader.wgsl`.
+    pub standard_shader: ShaderId,
+    /// Used for meshes without a [`Material`].
+    pub default_material: Material,
 }
 
 impl World {
     pub fn new(graphics: Rc<RefCell<WgpuGraphics>>) -> Self {
         let graphics = graphics.borrow();
-        let vertices: Vec<[f32; 3]> = vec![[0.0, 0.5, 0.0], [-0.5, -0.5, 0.0], [0.5, -0.5, 0.0]];
-        let vertex_buffer = graphics
+
+        let camera_buffer = graphics
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("World Vertex buffer"),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
+                label: Some("Camera buffer"),
+                contents: bytemuck::cast_slice(&[CameraUniform::default()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
-        let indices: Vec<u32> = vec![0, 1, 2];
-        let index_buffer = graphics
+        let camera_bind_group_layout = CameraUniform::bind_group_layout(&graphics.device);
+        let camera_bind_group = graphics
             .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("World Indices buffer"),
-                contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsages::INDEX,
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Camera bind group"),
+                layout: &camera_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                }],
             });
-        let num_elements = indices.len() as u32;
-
-        let render_pipeline_layout =
-            graphics
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipline Layout"),
-                    bind_group_layouts: &[],
-                    push_constant_ranges: &[],
-                });
-
-        let shader = graphics
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("Shader"),
-                source: wgpu::ShaderSource::Wgsl(
+
+        let material_bind_group_layout = MaterialUniform::bind_group_layout(&graphics.device);
+        let texture_bind_group_layout = Texture::bind_group_layout(&graphics.device);
+        let default_texture = Rc::new(Texture::from_color(
+            &graphics.device,
+            &graphics.queue,
+            [255, 255, 255, 255],
+        ));
+
+        let pipelines = PipelineCache::new(
+            &graphics.device,
+            &[
+                &camera_bind_group_layout,
+                &material_bind_group_layout,
+                &texture_bind_group_layout,
+            ],
+        );
+        let mut shaders = ShaderLibrary::new().with_capabilities(ShaderLibrary::get_capabilities(
+            graphics.device.features(),
+            graphics.adapter.get_downlevel_capabilities().flags,
+        ));
+        let standard_shader = shaders
+            .load_wgsl(
+                &graphics.device,
+                "shader",
+                get_shader_directory().join("shader.wgsl"),
+            )
+            .unwrap_or_else(|e| {
+                warn_core!("{}, falling back to the built in shader", e);
+                shaders.add_wgsl(
+                    &graphics.device,
+                    "shader",
                     include_str!(concat!(
                         env!("CARGO_MANIFEST_DIR"),
                         "/resources/shaders/shader.wgsl"
-                    ))
-                    .into(),
-                ),
+                    )),
+                )
             });
+        let default_material = Material::new(
+            &graphics.device,
+            &material_bind_group_layout,
+            &texture_bind_group_layout,
+            &default_texture,
+            MaterialDescriptor::new(standard_shader),
+        );
 
-        let buffer_layout = wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[wgpu::VertexAttribute {
-                format: wgpu::VertexFormat::Float32x3,
-                offset: 0,
-                shader_location: 0,
-            }],
-        };
+        Self {
+            components: Components::new(),
+            aspect_ratio: 1.0,
+            systems: Vec::new(),
+            fixed_systems: Vec::new(),
+            camera_buffer,
+            camera_bind_group,
+            material_bind_group_layout,
+            texture_bind_group_layout,
+            default_texture,
+            shaders,
+            pipelines,
+            standard_shader,
+            default_material,
+        }
+    }
+
+    /// Adds a shader to [`World::shaders`]. Replacing one with the same name drops the pipelines built from it.
+    pub fn add_shader(&mut self, device: &wgpu::Device, name: &str, source: &str) -> ShaderId {
+        let replaced = self.shaders.find(name);
+        let id = self.shaders.add_wgsl(device, name, source);
+        if replaced.is_some() {
+            self.pipelines.invalidate_shader(id);
+        }
+        id
+    }
+
+    /// Loads a watched shader file into [`World::shaders`], replacing like [`World::add_shader`].
+    pub fn load_shader(
+        &mut self,
+        device: &wgpu::Device,
+        name: &str,
+        path: impl AsRef<Path>,
+    ) -> SfResult<ShaderId> {
+        let replaced = self.shaders.find(name);
+        let id = self.shaders.load_wgsl(device, name, path)?;
+        if replaced.is_some() {
+            self.pipelines.invalidate_shader(id);
+        }
+        Ok(id)
+    }
 
-        let render_pipeline =
-            graphics
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("Render Pipeline"),
-                    layout: Some(&render_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        entry_point: Some("vs_main"),
-                        buffers: &[buffer_layout], //2
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        //3
-                        module: &shader,
-                        entry_point: Some("fs_main"),
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: graphics.surface_config.format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList, // 1.
-                        strip_index_format: None,
-                        cull_mode: Some(wgpu::Face::Back),
-                        front_face: wgpu::FrontFace::Ccw, // 2.
-                        // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        // Requires Features::DEPTH_CLIP_CONTROL
-                        unclipped_depth: false,
-                        // Requires Features::CONSERVATIVE_RASTERIZATION
-                        conservative: false,
-                    },
-                    depth_stencil: None, // 1.
-                    multisample: wgpu::MultisampleState {
-                        count: 1,                         // 2.
-                        mask: !0,                         // 3.
-                        alpha_to_coverage_enabled: false, // 4.
-                    },
-                    multiview: None, // 5.
-                    cache: None,     // 6.
-                });
+    /// Recompiles shaders whose files changed and rebuilds the pipelines using them.
+    /// On any error the previous shader and pipelines stay in use.
+    pub fn reload_changed_shaders(&mut self, device: &wgpu::Device) {
+        for change in self.shaders.poll_changes() {
+            let Some(shader) = self.shaders.get(change.id) else {
+                continue;
+            };
+            let name = shader.name.clone();
 
-        Self {
-            vertex_buffer,
-            index_buffer,
-            num_elements,
-            render_pipeline,
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let module = ShaderLibrary::create_module(device, &name, &change.source);
+            let pipelines = self
+                .pipelines
+                .create_pipelines_for_shader(device, change.id, &name, &module);
+            if let Some(e) = pollster::block_on(device.pop_error_scope()) {
+                error_core!(
+                    "failed to rebuild pipelines for shader {}, keeping the previous version:\n{}",
+                    name,
+                    e
+                );
+                continue;
+            }
+
+            self.shaders.replace_module(change.id, module);
+            self.pipelines.insert_pipelines(pipelines);
+            info_core!("reloaded shader {}", name);
+        }
+    }
+
+    /// Creates a material compatible with the world's render pipelines.
+    pub fn create_material(
+        &self,
+        device: &wgpu::Device,
+        descriptor: MaterialDescriptor,
+    ) -> Material {
+        Material::new(
+            device,
+            &self.material_bind_group_layout,
+            &self.texture_bind_group_layout,
+            &self.default_texture,
+            descriptor,
+        )
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        self.components.spawn()
+    }
+
+    /// Removes the entity together with all of its components.
+    /// Returns `false` if the entity was already despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        self.components.despawn(entity)
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.components.is_alive(entity)
+    }
+
+    pub fn entities(&self) -> &Entities {
+        self.components.entities()
+    }
+
+    /// Attaches a component to the entity, replacing and returning the previous one of the same type.
+    /// Components can't be attached to despawned entities, in that case it is handed back.
+    /// Cameras get the world's aspect ratio.
+    pub fn insert<T: Component>(&mut self, entity: Entity, mut component: T) -> Option<T> {
+        if let Some(camera) = (&mut component as &mut dyn Any).downcast_mut::<Camera>() {
+            camera.aspect_ratio = self.aspect_ratio;
         }
+        self.components.insert(entity, component)
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.components.remove(entity)
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.components.get(entity)
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.components.get_mut(entity)
+    }
+
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.components.has::<T>(entity)
+    }
+
+    pub fn storage<T: Component>(&self) -> Option<&ComponentStorage<T>> {
+        self.components.storage()
+    }
+
+    pub fn storage_mut<T: Component>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        self.components.storage_mut()
+    }
+
+    /// Iterates over entities having every component in `Q`, e.g. `world.query::<(&Transform, &Mesh)>()`.
+    pub fn query<Q: ReadOnlyQueryData>(&self) -> QueryIter<'_, Q> {
+        self.components.query()
+    }
+
+    /// Like [`World::query`] but allows `&mut` components, e.g. `world.query_mut::<(&mut Transform, &Velocity)>()`.
+    ///
+    /// Panics if the same component is requested twice and one of the requests is mutable.
+    pub fn query_mut<Q: QueryData>(&mut self) -> QueryIter<'_, Q> {
+        self.components.query_mut()
+    }
+
+    /// Adds a system run once per frame with the frame's timestep.
+    pub fn add_system<S: System + 'static>(&mut self, system: S) {
+        self.systems.push(Box::new(system));
+    }
+
+    /// Adds a system run at the fixed update rate, for physics and gameplay.
+    pub fn add_fixed_system<S: System + 'static>(&mut self, system: S) {
+        self.fixed_systems.push(Box::new(system));
+    }
+
+    /// Runs every frame system once, in the order they were added.
+    pub fn on_update(&mut self, timestep: Timestep) {
+        let mut systems = std::mem::take(&mut self.systems);
+        self.run_systems(&mut systems, timestep);
+        // Systems added while running are kept after the existing ones.
+        systems.append(&mut self.systems);
+        self.systems = systems;
+    }
+
+    /// Runs every fixed system once, in the order they were added.
+    pub fn on_fixed_update(&mut self, timestep: Timestep) {
+        let mut systems = std::mem::take(&mut self.fixed_systems);
+        self.run_systems(&mut systems, timestep);
+        systems.append(&mut self.fixed_systems);
+        self.fixed_systems = systems;
+    }
+
+    fn run_systems(&mut self, systems: &mut [Box<dyn System>], timestep: Timestep) {
+        for system in systems.iter_mut() {
+            system.run(self, timestep);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sf_graphics::material::{BlendMode, PipelineKey};
+
+    #[test]
+    fn replacing_a_shader_drops_its_pipelines() {
+        let Some(graphics) = WgpuGraphics::headless_for_tests(4) else {
+            return;
+        };
+        let graphics = Rc::new(RefCell::new(graphics));
+        let mut world = World::new(graphics.clone());
+        let graphics = graphics.borrow();
+        let key = PipelineKey {
+            shader: world.standard_shader,
+            blend_mode: BlendMode::Opaque,
+            cull_mode: None,
+            vertex_layout: VertexLayout::Standard,
+            target_format: graphics.surface_config.format,
+        };
+        world
+            .pipelines
+            .get_or_create(&graphics.device, &world.shaders, key)
+            .unwrap();
+
+        let source = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/resources/shaders/shader.wgsl"
+        ));
+        let id = world.add_shader(&graphics.device, "shader", source);
+
+        assert_eq!(id, world.standard_shader);
+        assert!(world.pipelines.is_empty());
     }
 }
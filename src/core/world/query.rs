@@ -0,0 +1,241 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use super::{
+    component::{Component, ComponentStorage, Components},
+    entity::Entity,
+};
+
+/// Something that can be fetched for an entity by a query: `&T`, `&mut T` or a tuple of those.
+///
+/// # Safety
+/// `access` must report every component `fetch` borrows, with the right mutability,
+/// so [`super::World::query_mut`] can reject queries that would alias.
+pub unsafe trait QueryData {
+    type State: Copy;
+    type Item<'w>;
+
+    fn access(access: &mut Vec<(TypeId, bool)>);
+
+    /// Resolves the storages the query reads. `None` if one of them doesn't exist yet,
+    /// which means no entity can match.
+    ///
+    /// # Safety
+    /// `components` must be valid for the whole lifetime of the returned state.
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State>;
+
+    /// Entities that may match, taken from the smallest storage involved.
+    ///
+    /// # Safety
+    /// `state` must come from `init_state` of components that is still alive.
+    unsafe fn candidates<'s>(state: Self::State) -> &'s [Entity];
+
+    /// # Safety
+    /// `state` must come from `init_state` of components that is still alive and the access
+    /// reported by `access` must not overlap with any other live borrow.
+    unsafe fn fetch<'w>(state: Self::State, entity: Entity) -> Option<Self::Item<'w>>;
+}
+
+/// Queries that never hand out `&mut`, usable from [`super::World::query`] on a shared world.
+///
+/// # Safety
+/// Must only be implemented for [`QueryData`] whose `fetch` doesn't write.
+pub unsafe trait ReadOnlyQueryData: QueryData {}
+
+unsafe impl<T: Component> QueryData for &T {
+    type State = *const ComponentStorage<T>;
+    type Item<'w> = &'w T;
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), false));
+    }
+
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+        unsafe { (*components).storage::<T>().map(|s| s as *const _) }
+    }
+
+    unsafe fn candidates<'s>(state: Self::State) -> &'s [Entity] {
+        unsafe { (*state).entities() }
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity: Entity) -> Option<Self::Item<'w>> {
+        unsafe { (*state).get(entity) }
+    }
+}
+unsafe impl<T: Component> ReadOnlyQueryData for &T {}
+
+unsafe impl<T: Component> QueryData for &mut T {
+    type State = *mut ComponentStorage<T>;
+    type Item<'w> = &'w mut T;
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), true));
+    }
+
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+        unsafe { (*components).storage_mut::<T>().map(|s| s as *mut _) }
+    }
+
+    unsafe fn candidates<'s>(state: Self::State) -> &'s [Entity] {
+        unsafe { (*state).entities() }
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity: Entity) -> Option<Self::Item<'w>> {
+        unsafe { ComponentStorage::get_ptr(state, entity).map(|c| &mut *c) }
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($name:ident : $idx:tt),*) => {
+        unsafe impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type State = ($($name::State,)*);
+            type Item<'w> = ($($name::Item<'w>,)*);
+
+            fn access(access: &mut Vec<(TypeId, bool)>) {
+                $($name::access(access);)*
+            }
+
+            unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+                unsafe { Some(($($name::init_state(components)?,)*)) }
+            }
+
+            unsafe fn candidates<'s>(state: Self::State) -> &'s [Entity] {
+                let mut smallest: Option<&'s [Entity]> = None;
+                $(
+                    let entities = unsafe { $name::candidates(state.$idx) };
+                    if smallest.is_none_or(|s| entities.len() < s.len()) {
+                        smallest = Some(entities);
+                    }
+                )*
+                smallest.unwrap_or(&[])
+            }
+
+            unsafe fn fetch<'w>(state: Self::State, entity: Entity) -> Option<Self::Item<'w>> {
+                unsafe { Some(($($name::fetch(state.$idx, entity)?,)*)) }
+            }
+        }
+
+        unsafe impl<$($name: ReadOnlyQueryData),*> ReadOnlyQueryData for ($($name,)*) {}
+    };
+}
+
+impl_query_data_tuple!(A: 0);
+impl_query_data_tuple!(A: 0, B: 1);
+impl_query_data_tuple!(A: 0, B: 1, C: 2);
+impl_query_data_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_query_data_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_query_data_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_query_data_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_query_data_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Iterates over every entity that has all components of `Q`.
+pub struct QueryIter<'w, Q: QueryData> {
+    state: Option<Q::State>,
+    entities: Vec<Entity>,
+    next: usize,
+    _components: PhantomData<&'w Components>,
+}
+
+impl<Q: QueryData> QueryIter<'_, Q> {
+    /// # Safety
+    /// The components behind `components` must outlive the iterator and must not be accessed in a way
+    /// that conflicts with `Q`'s access while the iterator or its items are alive.
+    pub(super) unsafe fn new(components: *mut Components) -> Self {
+        let state = unsafe { Q::init_state(components) };
+        let entities = match state {
+            Some(state) => unsafe { Q::candidates(state).to_vec() },
+            None => Vec::new(),
+        };
+
+        Self {
+            state,
+            entities,
+            next: 0,
+            _components: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q: QueryData> Iterator for QueryIter<'w, Q> {
+    type Item = (Entity, Q::Item<'w>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state?;
+
+        while let Some(entity) = self.entities.get(self.next).copied() {
+            self.next += 1;
+            // Entities are unique within a storage, so each item is handed out only once.
+            if let Some(item) = unsafe { Q::fetch(state, entity) } {
+                return Some((entity, item));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    struct Frozen;
+
+    #[test]
+    fn queries_entities_having_every_component() {
+        let mut components = Components::new();
+        let moving = components.spawn();
+        components.insert(moving, Position(0));
+        components.insert(moving, Velocity(2));
+        let still = components.spawn();
+        components.insert(still, Position(5));
+        let frozen = components.spawn();
+        components.insert(frozen, Velocity(1));
+        components.insert(frozen, Frozen);
+
+        for (_, (position, velocity)) in components.query_mut::<(&mut Position, &Velocity)>() {
+            position.0 += velocity.0;
+        }
+
+        let positions: Vec<_> = components.query::<&Position>().collect();
+        assert_eq!(positions, [(moving, &Position(2)), (still, &Position(5))]);
+        let moved: Vec<_> = components
+            .query::<(&Position, &Velocity)>()
+            .map(|(entity, _)| entity)
+            .collect();
+        assert_eq!(moved, [moving]);
+        assert_eq!(components.query::<(&Position, &Frozen)>().count(), 0);
+    }
+
+    #[test]
+    fn queries_skip_despawned_entities_and_missing_storages() {
+        let mut components = Components::new();
+        let entity = components.spawn();
+        components.insert(entity, Position(0));
+        assert_eq!(components.query::<(&Position, &Velocity)>().count(), 0);
+
+        components.despawn(entity);
+        assert_eq!(components.query::<&Position>().count(), 0);
+    }
+
+    #[test]
+    fn shared_reads_of_the_same_component_are_allowed() {
+        let mut components = Components::new();
+        let entity = components.spawn();
+        components.insert(entity, Position(1));
+
+        assert_eq!(components.query_mut::<(&Position, &Position)>().count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "borrows a component mutably more than once")]
+    fn aliasing_mutable_query_panics() {
+        let mut components = Components::new();
+        let entity = components.spawn();
+        components.insert(entity, Position(1));
+
+        components.query_mut::<(&mut Position, &Position)>().count();
+    }
+}
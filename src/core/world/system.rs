@@ -0,0 +1,17 @@
+use crate::core::sf_time::Timestep;
+
+use super::World;
+
+/// Game logic run by the world every update, in the order systems were added.
+pub trait System {
+    fn run(&mut self, world: &mut World, timestep: Timestep);
+}
+
+impl<F> System for F
+where
+    F: FnMut(&mut World, Timestep),
+{
+    fn run(&mut self, world: &mut World, timestep: Timestep) {
+        self(world, timestep)
+    }
+}
@@ -1,10 +1,10 @@
-use core::sf_layers::Layer;
+use core::sf_error::SfResult;
 
 pub mod application;
 pub mod core;
 pub mod sf_log;
 pub mod sf_window;
 
-pub fn entry_point() {
-    pollster::block_on(application::run());
+pub fn entry_point() -> SfResult<()> {
+    application::run()
 }
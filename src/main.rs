@@ -8,5 +8,8 @@ fn main() {
         }
     };
 
-    strife::entry_point();
+    if let Err(e) = strife::entry_point() {
+        error_client!("ENGINE ERROR: {}", e);
+        std::process::exit(1);
+    }
 }
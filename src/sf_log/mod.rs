@@ -28,7 +28,7 @@ impl LogColor {
     }
 }
 
-pub fn color_text(s: &String, log_color: LogColor) -> ColoredString {
+pub fn color_text(s: &str, log_color: LogColor) -> ColoredString {
     let (r, g, b) = log_color.value();
     s.truecolor(r, g, b)
 }
@@ -44,11 +44,7 @@ pub fn init() -> Option<&'static Logger> {
         }
     });
 
-    if inst.is_init {
-        return Some(inst);
-    } else {
-        return None;
-    }
+    if inst.is_init { Some(inst) } else { None }
 }
 
 fn setup_logger() -> Result<(), fern::InitError> {
@@ -1,34 +1,41 @@
 use std::{
     any::Any,
     cell::RefCell,
+    collections::HashMap,
     marker::PhantomData,
     ops::Deref,
-    path::Component,
     rc::Rc,
     sync::{Arc, Mutex},
 };
 
-use egui::ViewportId;
-use egui_winit::State;
 use wgpu::rwh::{
     HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle, WindowHandle,
 };
 use winit::{
+    application::ApplicationHandler,
     dpi::{PhysicalPosition, PhysicalSize},
-    error::EventLoopError,
-    event::{self, DeviceId, ElementState, KeyEvent, MouseButton, WindowEvent},
-    event_loop::{ActiveEventLoop, EventLoop, EventLoopBuilder, EventLoopProxy},
+    event::{self, DeviceId, ElementState, Ime, MouseButton},
+    event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
     keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowAttributes, WindowId},
 };
 
 use crate::{
-    core::sf_events::{
-        self, Event, Eventable, KeyPressedEvent, KeyReleasedEvent, MouseButtonPressedEvent,
-        MouseButtonReleasedEvent, MouseMoveEvent, WindowCloseEvent, WindowRedrawRequestedEvent,
-        WindowResizeEvent,
+    core::{
+        sf_error::{SfError, SfResult},
+        sf_events::{
+            AppAboutToWaitEvent, AppResumedEvent, AppSuspendedEvent, CursorEnteredEvent,
+            CursorLeftEvent, Eventable, FileDroppedEvent, GamepadAxisMovedEvent,
+            GamepadButtonPressedEvent, GamepadButtonReleasedEvent, GamepadConnectedEvent,
+            GamepadDisconnectedEvent, ImeEvent, KeyPressedEvent, KeyReleasedEvent,
+            ModifiersChangedEvent, MouseButtonPressedEvent, MouseButtonReleasedEvent,
+            MouseMoveEvent, MouseScrolledEvent, TextInputEvent, TouchEvent, WindowCloseEvent,
+            WindowFocusEvent, WindowMovedEvent, WindowOccludedEvent, WindowOpenedEvent,
+            WindowRedrawRequestedEvent, WindowResizeEvent, WindowScaleFactorChangedEvent,
+        },
+        sf_input::{GamepadEvent, Gamepads, Input},
     },
-    info_core,
+    error_core,
 };
 
 pub struct WindowWrapper<W> {
@@ -103,13 +110,65 @@ impl HasDisplayHandle for ThreadLockedRawWindowHandleWrapper {
 #[derive(Debug, Clone)]
 pub struct RawHandleWrapperHolder(pub Arc<Mutex<Option<RawWindowHandleWrapper>>>);
 
+/// Requests sent to the [`WindowManager`] through its `EventLoopProxy`, also from other threads.
 pub enum WindowManagerCustomEvent {
     TerminateWindow,
+    /// Opens a secondary window, announced with a [`WindowOpenedEvent`] carrying `label`.
+    OpenWindow {
+        label: String,
+        attributes: Box<WindowAttributes>,
+    },
+    /// Closes a secondary window, closing the main window exits the event loop.
+    CloseWindow(WindowId),
+    /// An event from another thread, see [`EventSender`](crate::core::sf_events::EventSender).
+    SendEvent(Box<dyn Eventable + Send>),
 }
 
 pub trait WindowEventListener {
-    fn on_raw_window_event(&mut self, event: winit::event::WindowEvent);
-    fn on_handled_event<T: Eventable>(&mut self, event: T);
+    fn on_raw_window_event(&mut self, window_id: WindowId, event: winit::event::WindowEvent);
+    /// Dispatches an engine event, afterwards the input state is updated from it.
+    fn on_handled_event<T: Eventable>(&mut self, event: &mut T);
+    /// Receives events sent from other threads through the event loop proxy.
+    fn on_sent_event(&mut self, event: Box<dyn Eventable>);
+    /// Called on every resume once the main window exists, surfaces for `windows` are created here.
+    /// An error stops the event loop and is returned from [`WindowManager::run`].
+    fn on_resumed(&mut self, windows: &Windows) -> SfResult<()>;
+    /// Called on suspend, surfaces have to be dropped here as the windows may stop being drawable.
+    fn on_suspended(&mut self, windows: &Windows);
+}
+
+/// Windows owned by the [`WindowManager`], keyed by id.
+#[derive(Default)]
+pub struct Windows {
+    windows: HashMap<WindowId, Arc<Window>>,
+    main_window_id: Option<WindowId>,
+}
+
+impl Windows {
+    pub fn get(&self, window_id: WindowId) -> Option<&Arc<Window>> {
+        self.windows.get(&window_id)
+    }
+
+    /// `None` until the event loop was resumed for the first time.
+    pub fn get_main_window_id(&self) -> Option<WindowId> {
+        self.main_window_id
+    }
+
+    pub fn get_main_window(&self) -> Option<&Arc<Window>> {
+        self.main_window_id.and_then(|id| self.windows.get(&id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Window>> {
+        self.windows.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
 }
 
 pub struct WindowEventHandler<H>
@@ -117,6 +176,8 @@ where
     H: WindowEventListener,
 {
     event_listener: Option<H>,
+    input: Rc<RefCell<Input>>,
+    gamepads: Gamepads,
 }
 
 impl<H> WindowEventHandler<H>
@@ -124,74 +185,287 @@ where
     H: WindowEventListener,
 {
     fn new(event_listener: Option<H>) -> WindowEventHandler<H> {
-        Self { event_listener }
+        Self {
+            event_listener,
+            input: Rc::new(RefCell::new(Input::new())),
+            gamepads: Gamepads::new(),
+        }
     }
     fn handle_window_event(
         &mut self,
-        event: &winit::event::Event<WindowManagerCustomEvent>,
+        window_id: WindowId,
+        event: winit::event::WindowEvent,
         elwt: &ActiveEventLoop,
-        window: &Window,
+        windows: &mut Windows,
     ) {
-        match *event {
-            winit::event::Event::WindowEvent {
-                ref event,
-                window_id,
-            } if window_id == window.id() => {
-                match &mut self.event_listener {
-                    Some(e) => e.on_raw_window_event(event.clone()),
-                    None => {}
-                }
+        if windows.get(window_id).is_none() {
+            return;
+        }
 
-                match event {
-                    event::WindowEvent::CloseRequested { .. } => {
-                        self.handle_window_close_event(window_id);
-                        elwt.exit();
-                    }
-                    event::WindowEvent::RedrawRequested {} => {
-                        window.request_redraw();
-                        self.handle_redraw_requested_event(window_id);
-                    }
-                    event::WindowEvent::KeyboardInput {
-                        event:
-                            event::KeyEvent {
-                                state,
-                                physical_key: PhysicalKey::Code(keycode),
-                                repeat,
-                                ..
-                            },
-                        ..
-                    } => {
-                        self.handle_keyboard_input(state, keycode, *repeat);
-                    }
-                    event::WindowEvent::Resized(physical_size) => {
-                        self.handle_window_resized_event(window_id, physical_size);
-                    }
-                    event::WindowEvent::MouseInput {
-                        device_id,
-                        state,
-                        button,
-                    } => {
-                        self.handle_mouse_input(state, *button, *device_id);
-                    }
-                    event::WindowEvent::CursorMoved {
-                        device_id,
-                        position,
-                    } => {
-                        self.handle_mouse_move_event(*device_id, position);
-                    }
+        if let Some(e) = &mut self.event_listener {
+            e.on_raw_window_event(window_id, event.clone());
+        }
 
-                    _ => {}
+        match event {
+            event::WindowEvent::CloseRequested => {
+                self.close_window(elwt, windows, window_id);
+            }
+            event::WindowEvent::RedrawRequested => {
+                self.handle_redraw_requested_event(window_id);
+                if windows.main_window_id == Some(window_id) {
+                    self.input.borrow_mut().end_frame();
                 }
             }
-
-            winit::event::Event::UserEvent(WindowManagerCustomEvent::TerminateWindow) => {
-                elwt.exit()
+            event::WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(keycode) = event.physical_key {
+                    self.handle_keyboard_input(window_id, &event.state, &keycode, event.repeat);
+                }
+                if event.state == ElementState::Pressed
+                    && let Some(text) = &event.text
+                {
+                    self.handle_text_input(window_id, text);
+                }
+            }
+            event::WindowEvent::ModifiersChanged(modifiers) => {
+                self.on_handled_event(ModifiersChangedEvent {
+                    name: String::from("ModifiersChangedEvent"),
+                    window_id,
+                    modifiers: modifiers.state(),
+                    is_handled: false,
+                });
+            }
+            event::WindowEvent::Ime(ime) => {
+                self.handle_ime(window_id, ime);
+            }
+            event::WindowEvent::Resized(physical_size) => {
+                self.handle_window_resized_event(window_id, &physical_size);
+            }
+            event::WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+            } => {
+                self.handle_mouse_input(window_id, &state, button, device_id);
+            }
+            event::WindowEvent::CursorMoved {
+                device_id,
+                position,
+            } => {
+                self.handle_mouse_move_event(window_id, device_id, &position);
+            }
+            event::WindowEvent::MouseWheel {
+                device_id,
+                delta,
+                phase,
+            } => {
+                self.on_handled_event(MouseScrolledEvent::from_delta(
+                    window_id, device_id, delta, phase,
+                ));
+            }
+            event::WindowEvent::CursorEntered { device_id } => {
+                self.on_handled_event(CursorEnteredEvent {
+                    name: String::from("CursorEnteredEvent"),
+                    is_handled: false,
+                    window_id,
+                    device_id,
+                });
+            }
+            event::WindowEvent::CursorLeft { device_id } => {
+                self.on_handled_event(CursorLeftEvent {
+                    name: String::from("CursorLeftEvent"),
+                    is_handled: false,
+                    window_id,
+                    device_id,
+                });
+            }
+            event::WindowEvent::Touch(touch) => {
+                self.on_handled_event(TouchEvent {
+                    name: String::from("TouchEvent"),
+                    is_handled: false,
+                    window_id,
+                    device_id: touch.device_id,
+                    finger_id: touch.id,
+                    phase: touch.phase,
+                    x: touch.location.x,
+                    y: touch.location.y,
+                    force: touch.force.map(|force| force.normalized()),
+                });
+            }
+            event::WindowEvent::Focused(is_focused) => {
+                self.on_handled_event(WindowFocusEvent {
+                    name: String::from("WINDOW FOCUS EVENT"),
+                    window_id,
+                    is_focused,
+                    is_handled: false,
+                });
+            }
+            event::WindowEvent::Moved(position) => {
+                self.on_handled_event(WindowMovedEvent {
+                    name: String::from("WINDOW MOVED EVENT"),
+                    window_id,
+                    x: position.x,
+                    y: position.y,
+                    is_handled: false,
+                });
+            }
+            event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.on_handled_event(WindowScaleFactorChangedEvent {
+                    name: String::from("WINDOW SCALE FACTOR CHANGED EVENT"),
+                    window_id,
+                    scale_factor,
+                    is_handled: false,
+                });
+            }
+            event::WindowEvent::Occluded(is_occluded) => {
+                self.on_handled_event(WindowOccludedEvent {
+                    name: String::from("WINDOW OCCLUDED EVENT"),
+                    window_id,
+                    is_occluded,
+                    is_handled: false,
+                });
+            }
+            event::WindowEvent::DroppedFile(path) => {
+                self.on_handled_event(FileDroppedEvent {
+                    name: String::from("FILE DROPPED EVENT"),
+                    window_id,
+                    path,
+                    is_handled: false,
+                });
             }
 
             _ => {}
-        };
+        }
+    }
+
+    fn handle_resumed(&mut self, windows: &Windows) -> SfResult<()> {
+        if let Some(e) = &mut self.event_listener {
+            e.on_resumed(windows)?;
+        }
+        self.on_handled_event(AppResumedEvent {
+            name: String::from("APP RESUMED EVENT"),
+            is_handled: false,
+        });
+        Ok(())
+    }
+
+    fn handle_suspended(&mut self, windows: &Windows) {
+        self.on_handled_event(AppSuspendedEvent {
+            name: String::from("APP SUSPENDED EVENT"),
+            is_handled: false,
+        });
+        if let Some(e) = &mut self.event_listener {
+            e.on_suspended(windows);
+        }
     }
 
+    fn handle_about_to_wait(&mut self) {
+        for event in self.gamepads.poll() {
+            self.handle_gamepad_event(event);
+        }
+        self.on_handled_event(AppAboutToWaitEvent {
+            name: String::from("APP ABOUT TO WAIT EVENT"),
+            is_handled: false,
+        });
+    }
+
+    fn handle_gamepad_event(&mut self, event: GamepadEvent) {
+        match event {
+            GamepadEvent::Connected(gamepad_id, gamepad_name) => {
+                self.on_handled_event(GamepadConnectedEvent {
+                    name: String::from("GAMEPAD CONNECTED EVENT"),
+                    gamepad_id,
+                    gamepad_name,
+                    is_handled: false,
+                });
+            }
+            GamepadEvent::Disconnected(gamepad_id) => {
+                self.on_handled_event(GamepadDisconnectedEvent {
+                    name: String::from("GAMEPAD DISCONNECTED EVENT"),
+                    gamepad_id,
+                    is_handled: false,
+                });
+            }
+            GamepadEvent::ButtonPressed(gamepad_id, button) => {
+                self.on_handled_event(GamepadButtonPressedEvent {
+                    name: String::from("GAMEPAD BUTTON PRESSED EVENT"),
+                    gamepad_id,
+                    button,
+                    is_handled: false,
+                });
+            }
+            GamepadEvent::ButtonReleased(gamepad_id, button) => {
+                self.on_handled_event(GamepadButtonReleasedEvent {
+                    name: String::from("GAMEPAD BUTTON RELEASED EVENT"),
+                    gamepad_id,
+                    button,
+                    is_handled: false,
+                });
+            }
+            GamepadEvent::AxisMoved(gamepad_id, axis, value) => {
+                self.on_handled_event(GamepadAxisMovedEvent {
+                    name: String::from("GAMEPAD AXIS MOVED EVENT"),
+                    gamepad_id,
+                    axis,
+                    value,
+                    is_handled: false,
+                });
+            }
+        }
+    }
+
+    fn handle_user_event(
+        &mut self,
+        event: WindowManagerCustomEvent,
+        elwt: &ActiveEventLoop,
+        windows: &mut Windows,
+    ) {
+        match event {
+            WindowManagerCustomEvent::TerminateWindow => elwt.exit(),
+            WindowManagerCustomEvent::OpenWindow { label, attributes } => {
+                match elwt.create_window(*attributes) {
+                    Ok(window) => {
+                        let window = Arc::new(window);
+                        windows.windows.insert(window.id(), window.clone());
+                        self.handle_window_opened_event(&label, window);
+                    }
+                    Err(e) => error_core!("failed to open window {}: {}", label, e),
+                }
+            }
+            WindowManagerCustomEvent::CloseWindow(window_id) => {
+                self.close_window(elwt, windows, window_id);
+            }
+            WindowManagerCustomEvent::SendEvent(event) => {
+                if let Some(e) = &mut self.event_listener {
+                    e.on_sent_event(event);
+                }
+            }
+        }
+    }
+
+    /// The close event is sent while the window is still alive, so listeners can drop its surface first.
+    fn close_window(&mut self, elwt: &ActiveEventLoop, windows: &mut Windows, window_id: WindowId) {
+        if windows.get(window_id).is_none() {
+            return;
+        }
+
+        self.handle_window_close_event(window_id);
+        if Some(window_id) == windows.main_window_id {
+            elwt.exit();
+        } else {
+            windows.windows.remove(&window_id);
+        }
+    }
+
+    fn handle_window_opened_event(&mut self, label: &str, window: Arc<Window>) {
+        let event = WindowOpenedEvent {
+            name: String::from("WINDOW OPENED EVENT"),
+            label: label.to_string(),
+            window,
+            is_handled: false,
+        };
+
+        self.on_handled_event(event);
+    }
     fn handle_window_close_event(&mut self, window_id: WindowId) {
         let event = WindowCloseEvent {
             name: String::from("WINDOW CLOSE EVENT"),
@@ -211,7 +485,13 @@ where
         self.on_handled_event(event);
     }
 
-    fn handle_keyboard_input(&mut self, state: &ElementState, keycode: &KeyCode, repeat: bool) {
+    fn handle_keyboard_input(
+        &mut self,
+        window_id: WindowId,
+        state: &ElementState,
+        keycode: &KeyCode,
+        repeat: bool,
+    ) {
         let is_pressed = *state == ElementState::Pressed;
 
         match is_pressed {
@@ -219,16 +499,50 @@ where
                 name: String::from("KeyPressedEvent"),
                 repeat,
                 is_handled: false,
+                window_id,
                 keycode: *keycode,
             }),
             false => self.on_handled_event(KeyReleasedEvent {
                 name: String::from("KeyRELEASED EVENT"),
                 keycode: *keycode,
+                window_id,
                 is_handled: false,
             }),
         };
     }
 
+    /// Control characters like backspace or escape are only reported as key presses.
+    fn handle_text_input(&mut self, window_id: WindowId, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if text.is_empty() {
+            return;
+        }
+
+        self.on_handled_event(TextInputEvent {
+            name: String::from("TextInputEvent"),
+            window_id,
+            text,
+            is_handled: false,
+        });
+    }
+
+    fn handle_ime(&mut self, window_id: WindowId, ime: Ime) {
+        let committed = match &ime {
+            Ime::Commit(text) => Some(text.clone()),
+            _ => None,
+        };
+
+        self.on_handled_event(ImeEvent {
+            name: String::from("ImeEvent"),
+            window_id,
+            ime,
+            is_handled: false,
+        });
+        if let Some(text) = committed {
+            self.handle_text_input(window_id, &text);
+        }
+    }
+
     fn handle_window_resized_event(
         &mut self,
         window_id: WindowId,
@@ -247,6 +561,7 @@ where
 
     fn handle_mouse_input(
         &mut self,
+        window_id: WindowId,
         state: &ElementState,
         button: MouseButton,
         device_id: DeviceId,
@@ -258,21 +573,29 @@ where
                 name: String::from("MousePressedEvent"),
                 button,
                 is_handled: false,
+                window_id,
                 device_id,
             }),
             false => self.on_handled_event(MouseButtonReleasedEvent {
                 name: String::from("MouseReleasedEvent"),
                 button,
                 is_handled: false,
+                window_id,
                 device_id,
             }),
         };
     }
 
-    fn handle_mouse_move_event(&mut self, device_id: DeviceId, position: &PhysicalPosition<f64>) {
+    fn handle_mouse_move_event(
+        &mut self,
+        window_id: WindowId,
+        device_id: DeviceId,
+        position: &PhysicalPosition<f64>,
+    ) {
         let event = MouseMoveEvent {
             name: String::from("WINDOW MOUSE MOVE EVENT"),
             is_handled: false,
+            window_id,
             device_id,
             x: position.x,
             y: position.y,
@@ -281,21 +604,27 @@ where
         self.on_handled_event(event);
     }
 
-    fn on_handled_event<T: Eventable>(&mut self, event: T) {
+    /// Updates the input state after the listener dispatched the event, so it can tell whether
+    /// e.g. the GUI captured it.
+    fn on_handled_event<T: Eventable>(&mut self, mut event: T) {
         if let Some(e) = &mut self.event_listener {
-            e.on_handled_event(event);
+            e.on_handled_event(&mut event);
         }
+        self.input.borrow_mut().on_event(&event);
     }
 }
 
+/// Owns every window, the main window being created with `main_window_attributes` on the first resume.
+/// Secondary windows are opened and closed by sending [`WindowManagerCustomEvent`]s through `event_loop_proxy`.
 pub struct WindowManager<H>
 where
     H: WindowEventListener,
 {
     pub event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
-    event_loop: winit::event_loop::EventLoop<WindowManagerCustomEvent>,
+    main_window_attributes: WindowAttributes,
     event_handler: WindowEventHandler<H>,
-    window: Arc<Window>,
+    windows: Windows,
+    exit_error: Option<SfError>,
 }
 
 impl<H> WindowManager<H>
@@ -304,17 +633,17 @@ where
 {
     pub fn new(
         event_listener: Option<H>,
-        event_loop: EventLoop<WindowManagerCustomEvent>,
         event_loop_proxy: EventLoopProxy<WindowManagerCustomEvent>,
-        window: Arc<Window>,
+        main_window_attributes: WindowAttributes,
     ) -> Self {
         let event_handler = WindowEventHandler::new(event_listener);
 
         Self {
-            window,
-            event_loop,
+            windows: Windows::default(),
+            main_window_attributes,
             event_handler,
             event_loop_proxy,
+            exit_error: None,
         }
     }
 
@@ -322,10 +651,81 @@ where
         self.event_handler.event_listener = event_listener;
     }
 
-    pub fn run(mut self) {
-        let _ = self.event_loop.run(move |event, elwt| {
-            self.event_handler
-                .handle_window_event(&event, elwt, &self.window)
-        });
+    pub fn get_windows(&self) -> &Windows {
+        &self.windows
+    }
+
+    /// Input state shared with the engine, updated from every window.
+    pub fn get_input(&self) -> Rc<RefCell<Input>> {
+        self.event_handler.input.clone()
+    }
+
+    /// Gamepad settings like the deadzone.
+    pub fn get_gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.event_handler.gamepads
+    }
+
+    /// Runs `event_loop` until the main window is closed or a resume fails.
+    pub fn run(mut self, event_loop: EventLoop<WindowManagerCustomEvent>) -> SfResult<()> {
+        event_loop.run_app(&mut self)?;
+
+        match self.exit_error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn exit_with_error(&mut self, elwt: &ActiveEventLoop, error: SfError) {
+        self.exit_error = Some(error);
+        elwt.exit();
+    }
+}
+
+impl<H> ApplicationHandler<WindowManagerCustomEvent> for WindowManager<H>
+where
+    H: WindowEventListener,
+{
+    fn resumed(&mut self, elwt: &ActiveEventLoop) {
+        if self.windows.main_window_id.is_none() {
+            match elwt.create_window(self.main_window_attributes.clone()) {
+                Ok(window) => {
+                    let window = Arc::new(window);
+                    self.windows.main_window_id = Some(window.id());
+                    self.windows.windows.insert(window.id(), window);
+                }
+                Err(e) => return self.exit_with_error(elwt, e.into()),
+            }
+        }
+
+        if let Err(e) = self.event_handler.handle_resumed(&self.windows) {
+            self.exit_with_error(elwt, e);
+        }
+    }
+
+    fn suspended(&mut self, _elwt: &ActiveEventLoop) {
+        self.event_handler.handle_suspended(&self.windows);
+    }
+
+    fn window_event(
+        &mut self,
+        elwt: &ActiveEventLoop,
+        window_id: WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        self.event_handler
+            .handle_window_event(window_id, event, elwt, &mut self.windows);
+    }
+
+    fn user_event(&mut self, elwt: &ActiveEventLoop, event: WindowManagerCustomEvent) {
+        self.event_handler
+            .handle_user_event(event, elwt, &mut self.windows);
+    }
+
+    /// Every window is redrawn continuously.
+    fn about_to_wait(&mut self, _elwt: &ActiveEventLoop) {
+        self.event_handler.handle_about_to_wait();
+        for window in self.windows.iter() {
+            window.request_redraw();
+        }
     }
 }